@@ -1,19 +1,33 @@
-use std::fs::File;
+use std::fs;
+use std::fs::{File, Metadata, Permissions};
 use std::io;
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use clap::Parser;
+use tempfile::Builder;
 
 #[derive(Debug, Parser)]
 #[command(version, about, author)]
 struct Args {
     #[arg(value_name = "IN_FILE", default_value = "-")]
-    in_file: String,
-    #[arg(value_name = "OUT_FILE")]
+    in_files: Vec<String>,
+    #[arg(
+        short,
+        long = "output",
+        value_name = "OUT_FILE",
+        help = "Write to OUT_FILE instead of stdout"
+    )]
     out_file: Option<String>,
     #[arg(short, long)]
     count: bool,
+    #[arg(
+        long,
+        conflicts_with = "count",
+        help = "Report counts per input file as a table"
+    )]
+    by_file: bool,
 }
 
 fn main() {
@@ -24,54 +38,198 @@ fn main() {
 }
 
 fn run(args: Args) -> Result<()> {
-    let mut file = open(&args.in_file).map_err(|e| anyhow!("{}: {e}", args.in_file))?;
-    let mut out_file: Box<dyn Write> = match &args.out_file {
-        Some(out_file) => Box::new(File::create(out_file)?),
-        None => Box::new(io::stdout()),
+    // OUT_FILE used to be the second positional argument. Catch the old
+    // `uniqr IN_FILE OUT_FILE` form rather than fail on a missing input.
+    if let [_, .., last] = &args.in_files[..] {
+        if args.out_file.is_none() && last != "-" && !Path::new(last).exists() {
+            bail!("{last}: no such input file; to write to it, use -o {last}");
+        }
+    }
+
+    let out_file = match &args.out_file {
+        Some(out_file) => out_file,
+        None => {
+            let files = open_all(&args.in_files)?;
+            return write_output(&args, files, &mut io::stdout().lock());
+        }
     };
 
-    let mut line = String::new();
-    let mut current_line: Option<String> = None;
-    let mut current_count = 1;
+    // Follows symlinks, so that they are written through rather than replaced.
+    let out_metadata = match fs::metadata(out_file) {
+        Ok(metadata) => Some(metadata),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => bail!("{out_file}: {e}"),
+    };
 
-    loop {
-        line.clear();
-        let bytes = file.read_line(&mut line)?;
-        if bytes == 0 {
-            break;
+    if let Some(out_metadata) = &out_metadata {
+        for in_file in &args.in_files {
+            if is_same_file(in_file, out_file, out_metadata)? {
+                bail!("{out_file}: input file is also the output file");
+            }
         }
+    }
 
-        match &mut current_line {
-            None => {
-                let current_line = current_line.insert(String::new());
-                std::mem::swap(current_line, &mut line);
-            }
-            Some(current_line) if current_line.trim_end() == line.trim_end() => {
-                current_count += 1;
+    let files = open_all(&args.in_files)?;
+
+    // FIFOs and devices like /dev/null can't be replaced, only written to.
+    let out_metadata = match out_metadata {
+        Some(metadata) if !metadata.is_file() => {
+            let file = File::create(out_file).map_err(|e| anyhow!("{out_file}: {e}"))?;
+            return write_output(&args, files, &mut BufWriter::new(file));
+        }
+        metadata => metadata,
+    };
+
+    let out_path = match out_metadata {
+        Some(_) => fs::canonicalize(out_file).map_err(|e| anyhow!("{out_file}: {e}"))?,
+        None => PathBuf::from(out_file),
+    };
+    // Write next to the destination so the final rename stays on one filesystem.
+    let dir = match out_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut builder = Builder::new();
+    if let Some(permissions) = out_metadata
+        .map(|metadata| metadata.permissions())
+        .or_else(default_permissions)
+    {
+        builder.permissions(permissions);
+    }
+    let tmp = builder
+        .tempfile_in(dir)
+        .map_err(|e| anyhow!("{out_file}: {e}"))?;
+
+    let mut writer = BufWriter::new(tmp);
+    write_output(&args, files, &mut writer)?;
+    let tmp = writer.into_inner().map_err(|e| e.into_error())?;
+    tmp.persist(&out_path)
+        .map_err(|e| anyhow!("{out_file}: {}", e.error))?;
+
+    Ok(())
+}
+
+fn write_output(
+    args: &Args,
+    files: Vec<Box<dyn BufRead>>,
+    out_file: &mut impl Write,
+) -> Result<()> {
+    if args.by_file {
+        writeln!(out_file, "total\t{}\tline", args.in_files.join("\t"))?;
+    }
+
+    for_each_run(files, |line, counts| {
+        let total: usize = counts.iter().sum();
+        if args.by_file {
+            let counts: Vec<String> = counts.iter().map(|c| c.to_string()).collect();
+            writeln!(
+                out_file,
+                "{total}\t{}\t{}",
+                counts.join("\t"),
+                line.trim_end_matches(['\r', '\n'])
+            )?;
+        } else if args.count {
+            write!(out_file, "{:4} {}", total, line)?;
+        } else {
+            write!(out_file, "{}", line)?;
+        }
+        Ok(())
+    })?;
+
+    out_file.flush()?;
+
+    Ok(())
+}
+
+/// Calls `f` with each run of adjacent equal lines across all `files`, read
+/// as one stream, along with how many lines of the run came from each file.
+fn for_each_run(
+    files: Vec<Box<dyn BufRead>>,
+    mut f: impl FnMut(&str, &[usize]) -> Result<()>,
+) -> Result<()> {
+    let mut line = String::new();
+    let mut current_line: Option<String> = None;
+    let mut counts = vec![0; files.len()];
+
+    for (i, mut file) in files.into_iter().enumerate() {
+        loop {
+            line.clear();
+            let bytes = file.read_line(&mut line)?;
+            if bytes == 0 {
+                break;
             }
-            Some(current_line) => {
-                if args.count {
-                    write!(&mut out_file, "{:4} {}", current_count, current_line)?;
-                } else {
-                    write!(&mut out_file, "{}", current_line)?;
+
+            match &mut current_line {
+                None => {
+                    let current_line = current_line.insert(String::new());
+                    std::mem::swap(current_line, &mut line);
+                }
+                Some(current_line) if current_line.trim_end() == line.trim_end() => {}
+                Some(current_line) => {
+                    // The last line of a file may lack a newline; don't glue it
+                    // to the first line of the next file.
+                    if !current_line.ends_with('\n') {
+                        current_line.push('\n');
+                    }
+                    f(current_line, &counts)?;
+                    counts.fill(0);
+                    std::mem::swap(current_line, &mut line);
                 }
-                current_count = 1;
-                std::mem::swap(current_line, &mut line);
             }
+            counts[i] += 1;
         }
     }
 
     if let Some(current_line) = current_line {
-        if args.count {
-            write!(&mut out_file, "{:4} {}", current_count, current_line)?;
-        } else {
-            write!(&mut out_file, "{}", current_line)?;
-        }
+        f(&current_line, &counts)?;
     }
 
     Ok(())
 }
 
+/// Compares inodes from metadata, since opening the output could block, as
+/// with a FIFO.
+#[cfg(unix)]
+fn is_same_file(in_file: &str, _out_file: &str, out_metadata: &Metadata) -> Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    if in_file == "-" {
+        return Ok(false);
+    }
+
+    let in_metadata = fs::metadata(in_file).map_err(|e| anyhow!("{in_file}: {e}"))?;
+    Ok(in_metadata.dev() == out_metadata.dev() && in_metadata.ino() == out_metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn is_same_file(in_file: &str, out_file: &str, _out_metadata: &Metadata) -> Result<bool> {
+    if in_file == "-" {
+        return Ok(false);
+    }
+
+    same_file::is_same_file(in_file, out_file).map_err(|e| anyhow!("{in_file}: {e}"))
+}
+
+#[cfg(unix)]
+fn default_permissions() -> Option<Permissions> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Subject to the umask, like `File::create`.
+    Some(Permissions::from_mode(0o666))
+}
+
+#[cfg(not(unix))]
+fn default_permissions() -> Option<Permissions> {
+    None
+}
+
+fn open_all(filenames: &[String]) -> Result<Vec<Box<dyn BufRead>>> {
+    filenames
+        .iter()
+        .map(|filename| open(filename).map_err(|e| anyhow!("{filename}: {e}")))
+        .collect()
+}
+
 fn open(filename: &str) -> Result<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(io::stdin()))),
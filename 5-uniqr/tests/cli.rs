@@ -4,6 +4,8 @@ use predicates::prelude::*;
 use pretty_assertions::assert_eq;
 use rand::{distributions::Alphanumeric, Rng};
 use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
 use tempfile::NamedTempFile;
 
 struct Test {
@@ -108,6 +110,203 @@ fn dies_bad_file() -> Result<()> {
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn dies_same_file() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("input.txt");
+    fs::copy(T1.input, &path)?;
+    let path = path.to_str().unwrap();
+
+    Command::cargo_bin(PRG)?
+        .args([path, "-o", path])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "input file is also the output file",
+        ));
+    assert_eq!(fs::read_to_string(path)?, fs::read_to_string(T1.input)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_same_file_hard_link() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("input.txt");
+    let link = dir.path().join("link.txt");
+    fs::copy(T1.input, &path)?;
+    fs::hard_link(&path, &link)?;
+
+    Command::cargo_bin(PRG)?
+        .args([path.to_str().unwrap(), "-o", link.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "input file is also the output file",
+        ));
+    assert_eq!(fs::read_to_string(path)?, fs::read_to_string(T1.input)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_by_file_count() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([T1.input, "--by-file", "-c"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn failed_run_keeps_outfile() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let input = dir.path().join("input.txt");
+    let outpath = dir.path().join("out.txt");
+    fs::write(&input, b"a\na\nb\n\xff\n")?;
+    fs::write(&outpath, "previous\n")?;
+
+    Command::cargo_bin(PRG)?
+        .args([input.to_str().unwrap(), "-o", outpath.to_str().unwrap()])
+        .assert()
+        .failure();
+    assert_eq!(fs::read_to_string(&outpath)?, "previous\n");
+    assert_eq!(fs::read_dir(dir.path())?.count(), 2);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn outfile_fifo() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let fifo = dir.path().join("out.fifo");
+    assert!(std::process::Command::new("mkfifo")
+        .arg(&fifo)
+        .status()?
+        .success());
+
+    let reader = {
+        let fifo = fifo.clone();
+        std::thread::spawn(move || fs::read_to_string(fifo))
+    };
+    Command::cargo_bin(PRG)?
+        .args([T1.input, "-o", fifo.to_str().unwrap()])
+        .timeout(std::time::Duration::from_secs(10))
+        .assert()
+        .success();
+    assert_eq!(reader.join().unwrap()?, fs::read_to_string(T1.out)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn outfile_symlink() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let target = dir.path().join("target.txt");
+    let link = dir.path().join("link.txt");
+    fs::write(&target, "previous\n")?;
+    std::os::unix::fs::symlink(&target, &link)?;
+
+    Command::cargo_bin(PRG)?
+        .args([T1.input, "-o", link.to_str().unwrap()])
+        .assert()
+        .success();
+    assert!(fs::symlink_metadata(&link)?.file_type().is_symlink());
+    assert_eq!(fs::read_to_string(&target)?, fs::read_to_string(T1.out)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn outfile_dev_null() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([T1.input, "-o", "/dev/null"])
+        .assert()
+        .success()
+        .stdout("");
+    assert!(fs::metadata("/dev/null")?.file_type().is_char_device());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_positional_outfile() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let outpath = dir.path().join("out.txt");
+    let outpath = outpath.to_str().unwrap();
+
+    Command::cargo_bin(PRG)?
+        .args([T1.input, outpath])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(format!(
+            "{outpath}: no such input file; to write to it, use -o {outpath}\n"
+        ));
+    assert!(fs::metadata(outpath).is_err());
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run_multi(args: &[&str], expected_file: &str) -> Result<()> {
+    let expected = fs::read_to_string(expected_file)?;
+    let output = Command::cargo_bin(PRG)?.args(args).output().expect("fail");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
+    assert_eq!(stdout, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn multi_count() -> Result<()> {
+    run_multi(
+        &[T1.input, T5.input, T6.input, "-c"],
+        "tests/expected/t1_t5_t6.txt.c.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn multi_no_trailing_newline() -> Result<()> {
+    run_multi(&[T3.input, T6.input], "tests/expected/t3_t6.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn multi_by_file() -> Result<()> {
+    run_multi(
+        &[T1.input, T5.input, T6.input, "--by-file"],
+        "tests/expected/t1_t5_t6.txt.by_file.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_same_file_multi() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("input.txt");
+    fs::copy(T1.input, &path)?;
+    let path = path.to_str().unwrap();
+
+    Command::cargo_bin(PRG)?
+        .args([T2.input, path, "-o", path])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "input file is also the output file",
+        ));
+    assert_eq!(fs::read_to_string(path)?, fs::read_to_string(T1.input)?);
+    Ok(())
+}
+
 // --------------------------------------------------
 // HELPER FUNCTIONS
 fn run(test: &Test) -> Result<()> {
@@ -175,7 +374,7 @@ fn run_outfile(test: &Test) -> Result<()> {
     let outpath = &outfile.path().to_str().unwrap();
 
     Command::cargo_bin(PRG)?
-        .args([test.input, outpath])
+        .args([test.input, "-o", outpath])
         .assert()
         .success()
         .stdout("");
@@ -191,7 +390,7 @@ fn run_outfile_count(test: &Test) -> Result<()> {
     let outpath = &outfile.path().to_str().unwrap();
 
     Command::cargo_bin(PRG)?
-        .args([test.input, outpath, "--count"])
+        .args([test.input, "-o", outpath, "--count"])
         .assert()
         .success()
         .stdout("");
@@ -210,7 +409,7 @@ fn run_stdin_outfile_count(test: &Test) -> Result<()> {
     let outpath = &outfile.path().to_str().unwrap();
 
     Command::cargo_bin(PRG)?
-        .args(["-", outpath, "-c"])
+        .args(["-", "-o", outpath, "-c"])
         .write_stdin(input)
         .assert()
         .stdout("");
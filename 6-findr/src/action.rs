@@ -0,0 +1,408 @@
+use std::cell::{Cell, RefCell};
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::format::{write_os_str, Format};
+use crate::predicate::Candidate;
+use crate::report::Report;
+
+/// An expression primary with a side effect. Any action in the expression
+/// disables the implicit `-print`.
+#[derive(Debug)]
+pub enum Action {
+    Print,
+    Print0,
+    Printf(Format),
+    /// Removes the entry. The walk visits directory contents first when the
+    /// expression contains this. Set once a deletion fails.
+    Delete(Cell<bool>),
+    Exec(Exec),
+    /// Stands in for `-print` with `--sort` or `--output`.
+    Report(Rc<Report>),
+}
+
+impl Action {
+    pub fn run(&self, candidate: &Candidate) -> Result<bool> {
+        let path = candidate.entry().path();
+        match self {
+            Self::Print | Self::Print0 => {
+                let mut stdout = io::stdout().lock();
+                write_os_str(&mut stdout, path.as_os_str())?;
+                stdout.write_all(if matches!(self, Self::Print0) {
+                    b"\0"
+                } else {
+                    b"\n"
+                })?;
+                Ok(true)
+            }
+            Self::Printf(format) => {
+                // Format into a buffer first so a failed directive doesn't
+                // leave a partial line behind.
+                let mut buf = vec![];
+                format.write(&mut buf, candidate)?;
+                io::stdout().lock().write_all(&buf)?;
+                Ok(true)
+            }
+            Self::Delete(failed) => {
+                delete(candidate).inspect_err(|_| failed.set(true))?;
+                Ok(true)
+            }
+            Self::Exec(exec) => exec.run(candidate),
+            Self::Report(report) => {
+                report.add(candidate)?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Runs whatever is still pending once the walk is over.
+    pub fn finish(&self) -> Result<()> {
+        match self {
+            Self::Exec(exec) => exec.finish(),
+            Self::Delete(failed) if failed.get() => bail!("-delete: some entries were not deleted"),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn delete(candidate: &Candidate) -> Result<()> {
+    let entry = candidate.entry();
+    let path = entry.path();
+    let error = |e: &dyn std::fmt::Display| anyhow!("{}: cannot delete: {e}", path.display());
+
+    // Like find, leave the current directory alone rather than fail on it.
+    if candidate.depth() == 0 && path == Path::new(".") {
+        return Ok(());
+    }
+
+    // Refuse anything that isn't really below the start path, such as the
+    // contents of a followed symlink.
+    if candidate.depth() > 0 {
+        let root = path.ancestors().nth(candidate.depth()).unwrap_or(path);
+        let root = fs::canonicalize(root).map_err(|e| error(&e))?;
+        let parent = path.parent().unwrap_or(path);
+        let parent = fs::canonicalize(parent).map_err(|e| error(&e))?;
+        if !parent.starts_with(&root) {
+            return Err(error(&"outside of the start path"));
+        }
+    }
+
+    if entry.file_type().is_dir() {
+        fs::remove_dir(path).map_err(|e| error(&e))
+    } else {
+        fs::remove_file(path).map_err(|e| error(&e))
+    }
+}
+
+/// `-exec`, `-execdir`, `-ok` and `-okdir`.
+#[derive(Debug)]
+pub struct Exec {
+    name: String,
+    /// The command and its arguments, with `{}` standing for the path.
+    command: Vec<String>,
+    /// Run from the directory containing the entry, passing `./NAME`.
+    in_dir: bool,
+    /// Ask for confirmation on stderr and read the answer from stdin.
+    prompt: bool,
+    /// Set for the `{} +` form, which collects paths like `xargs`.
+    batch: Option<RefCell<Batch>>,
+}
+
+impl Exec {
+    /// Parses the arguments of `name` up to and including the terminating
+    /// `;`, or `{} +`, returning the action and the number of tokens used.
+    pub fn parse(name: &str, tokens: &[String]) -> Result<(Self, usize)> {
+        let (in_dir, prompt) = match name {
+            "-exec" => (false, false),
+            "-execdir" => (true, false),
+            "-ok" => (false, true),
+            "-okdir" => (true, true),
+            _ => bail!("unknown action '{name}'"),
+        };
+
+        let Some(len) = command_len(tokens) else {
+            bail!("missing argument to '{name}'");
+        };
+        let mut command = tokens[..len].to_vec();
+        let batch = tokens[len] == "+";
+        if batch {
+            if prompt {
+                bail!("'{name} ... {{}} +' is not supported");
+            }
+            // Drop the trailing `{}`, which the batched paths replace.
+            command.pop();
+        }
+        if command.is_empty() {
+            bail!("missing command for '{name}'");
+        }
+        if batch && command.iter().any(|arg| arg.contains("{}")) {
+            bail!("only one instance of {{}} is supported with '{name} ... +'");
+        }
+
+        let exec = Self {
+            name: name.to_string(),
+            command,
+            in_dir,
+            prompt,
+            batch: batch.then(|| RefCell::new(Batch::new(arg_max()))),
+        };
+        Ok((exec, len + 1))
+    }
+
+    fn run(&self, candidate: &Candidate) -> Result<bool> {
+        let (dir, path) = self.target(candidate.entry().path());
+
+        if let Some(batch) = &self.batch {
+            let mut batch = batch.borrow_mut();
+            let fixed_len = self.command.iter().map(|arg| arg_size(arg.as_ref())).sum();
+            if batch.is_full(dir.as_deref(), &path, fixed_len) {
+                let (dir, paths) = batch.take();
+                if !self.spawn(dir.as_deref(), paths)? {
+                    batch.failed = true;
+                }
+            }
+            batch.push(dir, path);
+            return Ok(true);
+        }
+
+        let args: Vec<OsString> = self
+            .command
+            .iter()
+            .map(|arg| replace_braces(arg, &path))
+            .collect();
+        if self.prompt && !confirm(&args)? {
+            return Ok(false);
+        }
+        self.spawn(dir.as_deref(), args)
+    }
+
+    fn finish(&self) -> Result<()> {
+        let Some(batch) = &self.batch else {
+            return Ok(());
+        };
+
+        let mut batch = batch.borrow_mut();
+        let (dir, paths) = batch.take();
+        if !paths.is_empty() && !self.spawn(dir.as_deref(), paths)? {
+            batch.failed = true;
+        }
+        if batch.failed {
+            bail!(
+                "{}: {} returned a non-zero status",
+                self.name,
+                self.command[0]
+            );
+        }
+        Ok(())
+    }
+
+    /// The working directory and the path argument for `path`.
+    fn target(&self, path: &Path) -> (Option<PathBuf>, OsString) {
+        if !self.in_dir {
+            return (None, path.as_os_str().to_os_string());
+        }
+
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        (
+            Some(dir.to_path_buf()),
+            Path::new(".").join(name).into_os_string(),
+        )
+    }
+
+    /// Runs the command, appending `args` in batch mode or replacing it
+    /// otherwise, and returns whether it succeeded.
+    fn spawn(&self, dir: Option<&Path>, args: Vec<OsString>) -> Result<bool> {
+        let (program, args) = if self.batch.is_some() {
+            let fixed = self.command[1..].iter().map(OsString::from);
+            (
+                OsString::from(&self.command[0]),
+                fixed.chain(args).collect(),
+            )
+        } else {
+            let mut args = args.into_iter();
+            (args.next().unwrap_or_default(), args.collect::<Vec<_>>())
+        };
+
+        let mut command = Command::new(&program);
+        command.args(args);
+        if let Some(dir) = dir {
+            command.current_dir(dir);
+        }
+        if self.prompt {
+            command.stdin(Stdio::null());
+        }
+
+        // Keep our own output ordered before the child's.
+        io::stdout().flush()?;
+        let status = command
+            .status()
+            .map_err(|e| anyhow!("{}: {e}", program.to_string_lossy()))?;
+
+        Ok(status.success())
+    }
+}
+
+/// The paths collected by `-exec ... {} +`, run once they would exceed the
+/// argument size limit or, for `-execdir`, when the directory changes.
+#[derive(Debug)]
+struct Batch {
+    limit: usize,
+    dir: Option<PathBuf>,
+    paths: Vec<OsString>,
+    size: usize,
+    failed: bool,
+}
+
+impl Batch {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            dir: None,
+            paths: vec![],
+            size: 0,
+            failed: false,
+        }
+    }
+
+    fn is_full(&self, dir: Option<&Path>, path: &OsStr, fixed_len: usize) -> bool {
+        !self.paths.is_empty()
+            && (self.dir.as_deref() != dir || fixed_len + self.size + arg_size(path) > self.limit)
+    }
+
+    fn push(&mut self, dir: Option<PathBuf>, path: OsString) {
+        self.size += arg_size(&path);
+        self.dir = dir;
+        self.paths.push(path);
+    }
+
+    fn take(&mut self) -> (Option<PathBuf>, Vec<OsString>) {
+        self.size = 0;
+        (self.dir.take(), std::mem::take(&mut self.paths))
+    }
+}
+
+/// Number of tokens in the command before its `;` or `{} +` terminator.
+pub fn command_len(tokens: &[String]) -> Option<usize> {
+    tokens
+        .iter()
+        .enumerate()
+        .position(|(i, token)| token == ";" || (token == "+" && i > 0 && tokens[i - 1] == "{}"))
+}
+
+fn replace_braces(arg: &str, path: &OsStr) -> OsString {
+    let mut parts = arg.split("{}");
+    let mut replaced = OsString::from(parts.next().unwrap_or_default());
+    for part in parts {
+        replaced.push(path);
+        replaced.push(part);
+    }
+    replaced
+}
+
+/// Space taken by an argument: the string, its terminator and its pointer.
+fn arg_size(arg: &OsStr) -> usize {
+    arg.len() + 1 + std::mem::size_of::<usize>()
+}
+
+/// The space available for command-line arguments, after the environment
+/// and some headroom as POSIX recommends for `xargs`.
+fn arg_max() -> usize {
+    let env: usize = std::env::vars_os()
+        .map(|(key, value)| key.len() + value.len() + 2 + std::mem::size_of::<usize>())
+        .sum();
+    system_arg_max()
+        .saturating_sub(env)
+        .saturating_sub(2048)
+        .max(4096)
+}
+
+#[cfg(unix)]
+fn system_arg_max() -> usize {
+    // SAFETY: sysconf has no preconditions.
+    match unsafe { libc::sysconf(libc::_SC_ARG_MAX) } {
+        n if n > 0 => n as usize,
+        _ => 128 * 1024,
+    }
+}
+
+#[cfg(not(unix))]
+fn system_arg_max() -> usize {
+    // The limit on a Windows command line, in UTF-16 units.
+    32 * 1024
+}
+
+fn confirm(args: &[OsString]) -> Result<bool> {
+    let command: Vec<_> = args.iter().map(|arg| arg.to_string_lossy()).collect();
+    eprint!("< {} > ? ", command.join(" "));
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(answer.trim_start().starts_with(['y', 'Y']))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{OsStr, OsString};
+    use std::path::Path;
+
+    use super::{arg_size, command_len, replace_braces, Batch};
+
+    fn tokens(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_command_len() {
+        assert_eq!(command_len(&tokens("echo {} ;")), Some(2));
+        assert_eq!(command_len(&tokens("echo {} + ;")), Some(2));
+        assert_eq!(command_len(&tokens("echo + {} ;")), Some(3));
+        assert_eq!(command_len(&tokens("; -print")), Some(0));
+        assert_eq!(command_len(&tokens("echo {}")), None);
+        assert_eq!(command_len(&tokens("echo +")), None);
+    }
+
+    #[test]
+    fn test_replace_braces() {
+        let path = OsStr::new("a/b.txt");
+        assert_eq!(replace_braces("{}", path), "a/b.txt");
+        assert_eq!(replace_braces("{}.bak", path), "a/b.txt.bak");
+        assert_eq!(replace_braces("{}:{}", path), "a/b.txt:a/b.txt");
+        assert_eq!(replace_braces("-n", path), "-n");
+    }
+
+    #[test]
+    fn test_batch() {
+        let fixed_len = arg_size(OsStr::new("echo"));
+        let path = OsString::from("path");
+        let mut batch = Batch::new(fixed_len + 3 * arg_size(&path));
+
+        assert!(!batch.is_full(None, &path, fixed_len));
+        batch.push(None, path.clone());
+        batch.push(None, path.clone());
+        assert!(!batch.is_full(None, &path, fixed_len));
+        batch.push(None, path.clone());
+        assert!(batch.is_full(None, &path, fixed_len));
+
+        let (dir, paths) = batch.take();
+        assert_eq!(dir, None);
+        assert_eq!(paths.len(), 3);
+        assert!(!batch.is_full(None, &path, fixed_len));
+
+        // A new directory starts a new batch for -execdir.
+        batch.push(Some(Path::new("a").to_path_buf()), path.clone());
+        assert!(!batch.is_full(Some(Path::new("a")), &path, fixed_len));
+        assert!(batch.is_full(Some(Path::new("b")), &path, fixed_len));
+    }
+}
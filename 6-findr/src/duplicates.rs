@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::fs::{File, Metadata};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use serde_json::{json, Value};
+
+use crate::format::write_os_str;
+use crate::report::csv_field;
+use crate::Output;
+
+/// Number of bytes hashed to tell apart files of the same size.
+const PARTIAL_LEN: u64 = 4096;
+
+/// Collects regular files and finds groups with identical contents: first by
+/// size, then by a hash of their first block, then by a hash of the whole
+/// file. Of several hard links to one file, only the path that sorts first
+/// is kept.
+pub struct Duplicates {
+    min_size: u64,
+    /// Paths and sizes of the files collected.
+    files: Vec<(PathBuf, u64)>,
+    /// Indexes into `files` by device and inode.
+    links: HashMap<(u64, u64), usize>,
+}
+
+/// Files with the same contents, sorted by path.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Group {
+    pub size: u64,
+    pub paths: Vec<PathBuf>,
+}
+
+impl Duplicates {
+    pub fn new(min_size: u64) -> Self {
+        Self {
+            min_size,
+            files: vec![],
+            links: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, path: &Path, metadata: &Metadata) {
+        let size = metadata.len();
+        if size < self.min_size {
+            return;
+        }
+        if let Some(id) = file_id(metadata) {
+            if let Some(&i) = self.links.get(&id) {
+                if path < self.files[i].0.as_path() {
+                    self.files[i].0 = path.to_path_buf();
+                }
+                return;
+            }
+            self.links.insert(id, self.files.len());
+        }
+        self.files.push((path.to_path_buf(), size));
+    }
+
+    /// The groups of identical files, largest first. Files that can't be
+    /// read are reported and left out.
+    pub fn groups(self) -> Vec<Group> {
+        let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+        for (path, size) in self.files {
+            by_size.entry(size).or_default().push(path);
+        }
+
+        let mut groups = vec![];
+        for (size, paths) in by_size {
+            if paths.len() < 2 {
+                continue;
+            }
+
+            for paths in split_by_hash(paths, |path| hash(path, Some(PARTIAL_LEN))) {
+                if size <= PARTIAL_LEN {
+                    groups.push(Group { size, paths });
+                } else {
+                    groups.extend(
+                        split_by_hash(paths, |path| hash(path, None))
+                            .into_iter()
+                            .map(|paths| Group { size, paths }),
+                    );
+                }
+            }
+        }
+
+        for group in &mut groups {
+            group.paths.sort();
+        }
+        groups.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.paths.cmp(&b.paths)));
+        groups
+    }
+}
+
+/// Splits `paths` into groups of two or more with the same hash.
+fn split_by_hash(
+    paths: Vec<PathBuf>,
+    hash: impl Fn(&Path) -> io::Result<blake3::Hash>,
+) -> Vec<Vec<PathBuf>> {
+    let mut by_hash: HashMap<blake3::Hash, Vec<PathBuf>> = HashMap::new();
+    for path in paths {
+        match hash(&path) {
+            Ok(hash) => by_hash.entry(hash).or_default().push(path),
+            Err(e) => eprintln!("{}: {e}", path.display()),
+        }
+    }
+    by_hash
+        .into_values()
+        .filter(|paths| paths.len() > 1)
+        .collect()
+}
+
+/// Hashes the first `len` bytes of the file at `path`, or all of them.
+fn hash(path: &Path, len: Option<u64>) -> io::Result<blake3::Hash> {
+    let file = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    match len {
+        Some(len) => hasher.update_reader(file.take(len))?,
+        None => hasher.update_reader(file)?,
+    };
+    Ok(hasher.finalize())
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Writes each group as its paths on separate lines, with an empty line
+/// between groups, as a JSON array of `{"size": N, "paths": [...]}`, as one
+/// such object per line, or as CSV rows of group number, size and path.
+pub fn write(out: &mut impl Write, groups: &[Group], output: Output) -> Result<()> {
+    match output {
+        Output::Text => {
+            for (i, group) in groups.iter().enumerate() {
+                if i > 0 {
+                    writeln!(out)?;
+                }
+                for path in &group.paths {
+                    write_os_str(out, path.as_os_str())?;
+                    writeln!(out)?;
+                }
+            }
+        }
+        Output::Json => {
+            let groups: Vec<_> = groups.iter().map(to_json).collect();
+            serde_json::to_writer_pretty(&mut *out, &groups)?;
+            writeln!(out)?;
+        }
+        Output::Ndjson => {
+            for group in groups {
+                serde_json::to_writer(&mut *out, &to_json(group))?;
+                writeln!(out)?;
+            }
+        }
+        Output::Csv => {
+            writeln!(out, "group,size,path")?;
+            for (i, group) in groups.iter().enumerate() {
+                for path in &group.paths {
+                    let path = csv_field(&path.to_string_lossy());
+                    writeln!(out, "{},{},{path}", i + 1, group.size)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn to_json(group: &Group) -> Value {
+    let paths: Vec<_> = group.paths.iter().map(|p| p.to_string_lossy()).collect();
+    json!({ "size": group.size, "paths": paths })
+}
+
+/// Parses a `--min-size` of bytes, or of KiB, MiB or GiB with a `k`, `M` or
+/// `G` suffix.
+pub fn parse_size(s: &str) -> Result<u64> {
+    let (number, unit) = match s.char_indices().last() {
+        Some((i, 'k')) => (&s[..i], 1024),
+        Some((i, 'M')) => (&s[..i], 1024 * 1024),
+        Some((i, 'G')) => (&s[..i], 1024 * 1024 * 1024),
+        _ => (s, 1),
+    };
+
+    match number.parse::<u64>().ok().and_then(|n| n.checked_mul(unit)) {
+        Some(size) => Ok(size),
+        None => bail!("invalid size \"{s}\""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{parse_size, Duplicates, Group, PARTIAL_LEN};
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("0").unwrap(), 0);
+        assert_eq!(parse_size("10").unwrap(), 10);
+        assert_eq!(parse_size("2k").unwrap(), 2048);
+        assert_eq!(parse_size("1M").unwrap(), 1024 * 1024);
+        assert_eq!(parse_size("1G").unwrap(), 1024 * 1024 * 1024);
+
+        for bad in ["", "k", "-1", "1.5k", "1x", "99999999999G"] {
+            assert!(parse_size(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let long = vec![b'x'; PARTIAL_LEN as usize + 10];
+        let mut long_other = long.clone();
+        *long_other.last_mut().unwrap() = b'y';
+
+        let files: [(&str, &[u8]); 7] = [
+            ("a", b"same"),
+            ("b", b"same"),
+            ("c", b"diff"),
+            ("d", b"short"),
+            ("long1", &long),
+            ("long2", &long),
+            ("long3", &long_other),
+        ];
+        let mut duplicates = Duplicates::new(1);
+        for (name, contents) in files {
+            let path = dir.path().join(name);
+            fs::write(&path, contents).unwrap();
+            duplicates.add(&path, &fs::metadata(&path).unwrap());
+        }
+
+        let path = |name| dir.path().join(name);
+        assert_eq!(
+            duplicates.groups(),
+            [
+                Group {
+                    size: long.len() as u64,
+                    paths: vec![path("long1"), path("long2")],
+                },
+                Group {
+                    size: 4,
+                    paths: vec![path("a"), path("b")],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,540 @@
+use std::cell::Cell;
+use std::ffi::OsString;
+use std::fs;
+use std::time::SystemTime;
+
+use anyhow::{anyhow, bail, Result};
+use clap::ValueEnum;
+use regex::RegexBuilder;
+
+use crate::action::{self, Action, Exec};
+#[cfg(target_os = "linux")]
+use crate::mounts;
+use crate::predicate::{self, Candidate, Predicate};
+use crate::EntryType;
+
+/// A find-style expression built from predicates and the `!`/`-not`,
+/// `-a`/`-and`, `-o`/`-or` operators. Adjacent expressions are implicitly
+/// joined with `-and`.
+#[derive(Debug)]
+pub enum Expr {
+    Predicate(Predicate),
+    Action(Action),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    pub fn parse(tokens: &[String], now: SystemTime) -> Result<Self> {
+        if tokens.is_empty() {
+            return Ok(Self::Predicate(Predicate::True));
+        }
+
+        let mut parser = ExprParser {
+            tokens,
+            pos: 0,
+            now,
+        };
+        let expr = parser.parse_or()?;
+        match parser.next() {
+            None => Ok(expr),
+            Some(token) => bail!("unexpected '{token}' in expression"),
+        }
+    }
+
+    /// Joins `exprs` with `-and`; an empty list always matches.
+    pub fn all(exprs: impl IntoIterator<Item = Expr>) -> Self {
+        exprs
+            .into_iter()
+            .reduce(|lhs, rhs| Self::And(Box::new(lhs), Box::new(rhs)))
+            .unwrap_or(Self::Predicate(Predicate::True))
+    }
+
+    /// Joins `exprs` with `-or`; an empty list never matches.
+    pub fn any(exprs: impl IntoIterator<Item = Expr>) -> Self {
+        exprs
+            .into_iter()
+            .reduce(|lhs, rhs| Self::Or(Box::new(lhs), Box::new(rhs)))
+            .unwrap_or(Self::Predicate(Predicate::False))
+    }
+
+    pub fn is_match(&self, candidate: &Candidate) -> Result<bool> {
+        match self {
+            Self::Predicate(predicate) => predicate.is_match(candidate),
+            Self::Action(action) => action.run(candidate),
+            Self::Not(expr) => Ok(!expr.is_match(candidate)?),
+            Self::And(lhs, rhs) => Ok(lhs.is_match(candidate)? && rhs.is_match(candidate)?),
+            Self::Or(lhs, rhs) => Ok(lhs.is_match(candidate)? || rhs.is_match(candidate)?),
+        }
+    }
+
+    pub fn has_action(&self) -> bool {
+        self.any_action(&|_| true)
+    }
+
+    pub fn any_action(&self, f: &dyn Fn(&Action) -> bool) -> bool {
+        match self {
+            Self::Predicate(_) => false,
+            Self::Action(action) => f(action),
+            Self::Not(expr) => expr.any_action(f),
+            Self::And(lhs, rhs) | Self::Or(lhs, rhs) => lhs.any_action(f) || rhs.any_action(f),
+        }
+    }
+
+    /// Replaces each `-print` with `action()`.
+    pub fn replace_print(self, action: &dyn Fn() -> Action) -> Self {
+        match self {
+            Self::Action(Action::Print) => Self::Action(action()),
+            Self::Not(expr) => Self::Not(Box::new(expr.replace_print(action))),
+            Self::And(lhs, rhs) => Self::And(
+                Box::new(lhs.replace_print(action)),
+                Box::new(rhs.replace_print(action)),
+            ),
+            Self::Or(lhs, rhs) => Self::Or(
+                Box::new(lhs.replace_print(action)),
+                Box::new(rhs.replace_print(action)),
+            ),
+            expr => expr,
+        }
+    }
+
+    /// Completes the actions that defer work until the walk is over, such as
+    /// `-exec ... {} +`.
+    pub fn finish(&self) -> Result<()> {
+        match self {
+            Self::Predicate(_) => Ok(()),
+            Self::Action(action) => action.finish(),
+            Self::Not(expr) => expr.finish(),
+            Self::And(lhs, rhs) | Self::Or(lhs, rhs) => {
+                let result = lhs.finish();
+                rhs.finish().and(result)
+            }
+        }
+    }
+}
+
+/// Number of operands taken by an expression token, or `None` if `token`
+/// isn't part of the expression language.
+fn arity(token: &str) -> Option<usize> {
+    match token {
+        "(" | ")" | "!" | "-not" | "-a" | "-and" | "-o" | "-or" => Some(0),
+        "-true" | "-false" | "-empty" | "-prune" | "-nouser" | "-nogroup" => Some(0),
+        "-print" | "-print0" | "-delete" => Some(0),
+        "-name" | "-iname" | "-path" | "-ipath" | "-regex" | "-iregex" => Some(1),
+        "-type" | "-xtype" | "-size" | "-mtime" | "-mmin" | "-newer" | "-printf" => Some(1),
+        "-user" | "-group" | "-perm" | "-links" | "-fstype" => Some(1),
+        _ => None,
+    }
+}
+
+/// Separates expression tokens and their operands from the arguments meant
+/// for clap, so that options and paths may be mixed with the expression.
+pub fn split_args(
+    args: impl IntoIterator<Item = OsString>,
+) -> Result<(Vec<OsString>, Vec<String>)> {
+    let mut cli_args = vec![];
+    let mut expression = vec![];
+
+    let mut args = args.into_iter();
+    cli_args.extend(args.next());
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            cli_args.push(arg);
+            cli_args.extend(args);
+            break;
+        }
+
+        // As in GNU find, -xdev applies to the whole walk wherever it is.
+        if arg == "-xdev" {
+            cli_args.push("--one-file-system".into());
+            continue;
+        }
+
+        if let Some(token @ ("-exec" | "-execdir" | "-ok" | "-okdir")) = arg.to_str() {
+            let token = token.to_string();
+            let mut command = vec![];
+            for operand in args.by_ref() {
+                command.push(operand.into_string().map_err(|operand| {
+                    anyhow!(
+                        "invalid UTF-8 in argument '{}' to '{token}'",
+                        operand.to_string_lossy()
+                    )
+                })?);
+                if action::command_len(&command).is_some() {
+                    break;
+                }
+            }
+            if action::command_len(&command).is_none() {
+                bail!("missing argument to '{token}'");
+            }
+            expression.push(token);
+            expression.extend(command);
+            continue;
+        }
+
+        let Some(arity) = arg.to_str().and_then(arity) else {
+            cli_args.push(arg);
+            continue;
+        };
+
+        let token = arg.into_string().unwrap_or_default();
+        let mut operands = Vec::with_capacity(arity);
+        for _ in 0..arity {
+            let operand = args
+                .next()
+                .ok_or_else(|| anyhow!("missing argument to '{token}'"))?
+                .into_string()
+                .map_err(|operand| {
+                    anyhow!(
+                        "invalid UTF-8 in argument '{}' to '{token}'",
+                        operand.to_string_lossy()
+                    )
+                })?;
+            operands.push(operand);
+        }
+        expression.push(token);
+        expression.extend(operands);
+    }
+
+    Ok((cli_args, expression))
+}
+
+struct ExprParser<'a> {
+    tokens: &'a [String],
+    pos: usize,
+    now: SystemTime,
+}
+
+impl<'a> ExprParser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.peek()?;
+        self.pos += 1;
+        Some(token)
+    }
+
+    fn operand(&mut self, token: &str) -> Result<&'a str> {
+        self.next()
+            .ok_or_else(|| anyhow!("missing argument to '{token}'"))
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while let Some("-o" | "-or") = self.peek() {
+            self.pos += 1;
+            let rhs = self.parse_and()?;
+            expr = Expr::Or(Box::new(expr), Box::new(rhs));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_not()?;
+        loop {
+            match self.peek() {
+                None | Some(")" | "-o" | "-or") => break,
+                Some("-a" | "-and") => self.pos += 1,
+                Some(_) => {}
+            }
+            let rhs = self.parse_not()?;
+            expr = Expr::And(Box::new(expr), Box::new(rhs));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Some("!" | "-not") => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.parse_not()?)))
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        let Some(token) = self.next() else {
+            bail!("expected an expression at end of input");
+        };
+
+        match token {
+            "(" => {
+                if self.peek() == Some(")") {
+                    bail!("empty parentheses are not allowed");
+                }
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(")") => Ok(expr),
+                    _ => bail!("missing ')' in expression"),
+                }
+            }
+            ")" | "-a" | "-and" | "-o" | "-or" => {
+                bail!("expected an expression before '{token}'")
+            }
+            "-print" => Ok(Expr::Action(Action::Print)),
+            "-print0" => Ok(Expr::Action(Action::Print0)),
+            "-delete" => Ok(Expr::Action(Action::Delete(Cell::new(false)))),
+            "-printf" => {
+                let arg = self.operand(token)?;
+                let format = arg
+                    .parse()
+                    .map_err(|e| anyhow!("invalid value '{arg}' for '{token}': {e}"))?;
+                Ok(Expr::Action(Action::Printf(format)))
+            }
+            "-exec" | "-execdir" | "-ok" | "-okdir" => {
+                let (exec, len) = Exec::parse(token, &self.tokens[self.pos..])?;
+                self.pos += len;
+                Ok(Expr::Action(Action::Exec(exec)))
+            }
+            _ => self.parse_predicate(token).map(Expr::Predicate),
+        }
+    }
+
+    fn parse_predicate(&mut self, token: &str) -> Result<Predicate> {
+        let invalid = |arg: &str, e: &dyn std::fmt::Display| {
+            anyhow!("invalid value '{arg}' for '{token}': {e}")
+        };
+
+        match token {
+            "-true" => Ok(Predicate::True),
+            "-false" => Ok(Predicate::False),
+            "-empty" => Ok(Predicate::Empty),
+            "-prune" => Ok(Predicate::Prune),
+            "-name" | "-iname" => {
+                let arg = self.operand(token)?;
+                predicate::glob(arg, token == "-iname")
+                    .map(Predicate::Name)
+                    .map_err(|e| invalid(arg, &e))
+            }
+            "-path" | "-ipath" => {
+                let arg = self.operand(token)?;
+                predicate::glob(arg, token == "-ipath")
+                    .map(Predicate::Path)
+                    .map_err(|e| invalid(arg, &e))
+            }
+            "-regex" | "-iregex" => {
+                let arg = self.operand(token)?;
+                // Like find, the regex has to match the whole path.
+                RegexBuilder::new(&format!("^(?:{})$", arg))
+                    .case_insensitive(token == "-iregex")
+                    .build()
+                    .map(Predicate::Regex)
+                    .map_err(|e| invalid(arg, &e))
+            }
+            "-type" | "-xtype" => {
+                let arg = self.operand(token)?;
+                let entry_types = arg
+                    .split(',')
+                    .map(|t| EntryType::from_str(t, false).map_err(|e| invalid(arg, &e)))
+                    .collect::<Result<_>>()?;
+                Ok(if token == "-type" {
+                    Predicate::Type(entry_types)
+                } else {
+                    Predicate::XType(entry_types)
+                })
+            }
+            "-size" => {
+                let arg = self.operand(token)?;
+                arg.parse()
+                    .map(Predicate::Size)
+                    .map_err(|e| invalid(arg, &e))
+            }
+            "-mtime" => {
+                let arg = self.operand(token)?;
+                let comparison = arg.parse().map_err(|e| invalid(arg, &e))?;
+                Ok(Predicate::MTime {
+                    comparison,
+                    now: self.now,
+                })
+            }
+            "-mmin" => {
+                let arg = self.operand(token)?;
+                let comparison = arg.parse().map_err(|e| invalid(arg, &e))?;
+                Ok(Predicate::MMin {
+                    comparison,
+                    now: self.now,
+                })
+            }
+            "-newer" => {
+                let arg = self.operand(token)?;
+                newer(arg).map(Predicate::Newer)
+            }
+            #[cfg(unix)]
+            "-user" => {
+                let arg = self.operand(token)?;
+                uzers::get_user_by_name(arg)
+                    .map(|user| user.uid())
+                    .or_else(|| arg.parse().ok())
+                    .map(Predicate::User)
+                    .ok_or_else(|| invalid(arg, &"no such user"))
+            }
+            #[cfg(unix)]
+            "-group" => {
+                let arg = self.operand(token)?;
+                uzers::get_group_by_name(arg)
+                    .map(|group| group.gid())
+                    .or_else(|| arg.parse().ok())
+                    .map(Predicate::Group)
+                    .ok_or_else(|| invalid(arg, &"no such group"))
+            }
+            #[cfg(unix)]
+            "-nouser" => Ok(Predicate::NoUser),
+            #[cfg(unix)]
+            "-nogroup" => Ok(Predicate::NoGroup),
+            #[cfg(unix)]
+            "-perm" => {
+                let arg = self.operand(token)?;
+                arg.parse()
+                    .map(Predicate::Perm)
+                    .map_err(|e| invalid(arg, &e))
+            }
+            #[cfg(target_os = "linux")]
+            "-fstype" => {
+                let arg = self.operand(token)?;
+                mounts::devices(arg).map(Predicate::FsType)
+            }
+            #[cfg(unix)]
+            "-links" => {
+                let arg = self.operand(token)?;
+                arg.parse()
+                    .map(Predicate::Links)
+                    .map_err(|e| invalid(arg, &e))
+            }
+            _ => bail!("unknown predicate '{token}'"),
+        }
+    }
+}
+
+/// Modification time of the `-newer` reference file.
+pub fn newer(file: &str) -> Result<SystemTime> {
+    fs::metadata(file)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| anyhow!("{file}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+    use std::time::SystemTime;
+
+    use super::{split_args, Expr};
+
+    fn tokens(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    fn show(expr: &Expr) -> String {
+        match expr {
+            Expr::Predicate(predicate) => format!("{predicate:?}"),
+            Expr::Action(action) => format!("{action:?}"),
+            Expr::Not(expr) => format!("!{}", show(expr)),
+            Expr::And(lhs, rhs) => format!("({} & {})", show(lhs), show(rhs)),
+            Expr::Or(lhs, rhs) => format!("({} | {})", show(lhs), show(rhs)),
+        }
+    }
+
+    fn parse(s: &str) -> String {
+        show(&Expr::parse(&tokens(s), SystemTime::now()).unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(""), "True");
+        assert_eq!(parse("-true"), "True");
+        assert_eq!(parse("-true -false"), "(True & False)");
+        assert_eq!(parse("-true -a -false"), "(True & False)");
+        assert_eq!(parse("-true -and -false"), "(True & False)");
+        assert_eq!(parse("-true -o -false"), "(True | False)");
+        assert_eq!(parse("-true -or -false"), "(True | False)");
+        assert_eq!(parse("! -true"), "!True");
+        assert_eq!(parse("-not -not -true"), "!!True");
+
+        // -and binds tighter than -or, and ! tighter than both
+        assert_eq!(parse("-true -o -false -empty"), "(True | (False & Empty))");
+        assert_eq!(
+            parse("( -true -o -false ) -empty"),
+            "((True | False) & Empty)"
+        );
+        assert_eq!(parse("! -true -false"), "(!True & False)");
+        assert_eq!(
+            parse("-true -o -false -o -empty"),
+            "((True | False) | Empty)"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let now = SystemTime::now();
+        for bad in [
+            "(",
+            ")",
+            "( )",
+            "( -true",
+            "-true )",
+            "-o -true",
+            "-true -o",
+            "!",
+            "-name",
+            "-name [",
+            "-ipath {a",
+            "-regex (",
+            "-user",
+            "-user no-such-user",
+            "-group no-such-group",
+            "-perm 8",
+            "-perm u+q",
+            "-links x",
+            "-type x",
+            "-xtype",
+            "-xtype d,",
+            "-size 1x",
+            "-mtime 1d",
+            "-exec ;",
+            "-exec {} +",
+            "-printf",
+            "-printf %z",
+            "-ok echo {} +",
+            "-exec echo {} {} +",
+        ] {
+            assert!(Expr::parse(&tokens(bad), now).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_split_args() {
+        let args =
+            ["findr", "a", "(", "-name", "-o", "-o", "-t", "f", ")", "b"].map(OsString::from);
+        let (cli_args, expression) = split_args(args).unwrap();
+        assert_eq!(cli_args, ["findr", "a", "-t", "f", "b"]);
+        assert_eq!(expression, ["(", "-name", "-o", "-o", ")"]);
+
+        let args = ["findr", "--", "-name"].map(OsString::from);
+        let (cli_args, expression) = split_args(args).unwrap();
+        assert_eq!(cli_args, ["findr", "--", "-name"]);
+        assert!(expression.is_empty());
+
+        assert!(split_args(["findr", "-name"].map(OsString::from)).is_err());
+
+        let args = ["findr", "-exec", "mv", "-t", "{}", ";", "-t", "f"].map(OsString::from);
+        let (cli_args, expression) = split_args(args).unwrap();
+        assert_eq!(cli_args, ["findr", "-t", "f"]);
+        assert_eq!(expression, ["-exec", "mv", "-t", "{}", ";"]);
+
+        let args = ["findr", "-exec", "echo", "+", "{}", "+", "a"].map(OsString::from);
+        let (cli_args, expression) = split_args(args).unwrap();
+        assert_eq!(cli_args, ["findr", "a"]);
+        assert_eq!(expression, ["-exec", "echo", "+", "{}", "+"]);
+
+        assert!(split_args(["findr", "-exec", "echo", "{}"].map(OsString::from)).is_err());
+
+        let args = ["findr", "-true", "-xdev", "a"].map(OsString::from);
+        let (cli_args, expression) = split_args(args).unwrap();
+        assert_eq!(cli_args, ["findr", "--one-file-system", "a"]);
+        assert_eq!(expression, ["-true"]);
+    }
+}
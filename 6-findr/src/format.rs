@@ -0,0 +1,255 @@
+use std::ffi::OsStr;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{bail, Error, Result};
+use chrono::{DateTime, Local};
+
+use crate::predicate::Candidate;
+
+/// A `-printf` format string.
+#[derive(Debug)]
+pub struct Format {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum Segment {
+    Literal(String),
+    /// `%p`
+    Path,
+    /// `%f`
+    Name,
+    /// `%h`
+    Dir,
+    /// `%s`
+    Size,
+    /// `%m`
+    Mode,
+    /// `%u`
+    User,
+    /// `%g`
+    Group,
+    /// `%t`
+    MTime,
+    /// `%y`
+    Type,
+    /// `%d`
+    Depth,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut segments = vec![];
+        let mut literal = String::new();
+
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            let segment = match c {
+                '\\' => {
+                    match chars.next() {
+                        Some('n') => literal.push('\n'),
+                        Some('t') => literal.push('\t'),
+                        Some('r') => literal.push('\r'),
+                        Some('0') => literal.push('\0'),
+                        Some('\\') => literal.push('\\'),
+                        Some(c) => {
+                            literal.push('\\');
+                            literal.push(c);
+                        }
+                        None => literal.push('\\'),
+                    }
+                    continue;
+                }
+                '%' => match chars.next() {
+                    Some('%') => {
+                        literal.push('%');
+                        continue;
+                    }
+                    Some('p') => Segment::Path,
+                    Some('f') => Segment::Name,
+                    Some('h') => Segment::Dir,
+                    Some('s') => Segment::Size,
+                    Some('m') => Segment::Mode,
+                    Some('u') => Segment::User,
+                    Some('g') => Segment::Group,
+                    Some('t') => Segment::MTime,
+                    Some('y') => Segment::Type,
+                    Some('d') => Segment::Depth,
+                    Some(c) => bail!("unknown format directive '%{c}'"),
+                    None => bail!("incomplete format directive at end of \"{s}\""),
+                },
+                c => {
+                    literal.push(c);
+                    continue;
+                }
+            };
+
+            if !literal.is_empty() {
+                segments.push(Segment::Literal(std::mem::take(&mut literal)));
+            }
+            segments.push(segment);
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Self { segments })
+    }
+}
+
+impl Format {
+    pub fn write(&self, out: &mut impl Write, candidate: &Candidate) -> Result<()> {
+        let entry = candidate.entry();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(s) => out.write_all(s.as_bytes())?,
+                Segment::Path => write_os_str(out, entry.path().as_os_str())?,
+                Segment::Name => write_os_str(out, entry.file_name())?,
+                Segment::Dir => {
+                    let dir = match entry.path().parent() {
+                        Some(dir) if !dir.as_os_str().is_empty() => dir,
+                        _ => Path::new("."),
+                    };
+                    write_os_str(out, dir.as_os_str())?
+                }
+                Segment::Size => write!(out, "{}", candidate.metadata()?.len())?,
+                Segment::Mode => write!(out, "{}", mode(candidate)?)?,
+                Segment::User => write!(out, "{}", user(candidate)?)?,
+                Segment::Group => write!(out, "{}", group(candidate)?)?,
+                Segment::MTime => {
+                    let modified: DateTime<Local> = candidate.metadata()?.modified()?.into();
+                    write!(out, "{}", modified.format("%a %b %e %H:%M:%S %Y"))?
+                }
+                Segment::Type => write!(out, "{}", type_char(candidate))?,
+                Segment::Depth => write!(out, "{}", candidate.depth())?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes `s` as is where possible, rather than lossily converting it.
+pub fn write_os_str(out: &mut impl Write, s: &OsStr) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        out.write_all(s.as_bytes())
+    }
+
+    #[cfg(not(unix))]
+    {
+        out.write_all(s.to_string_lossy().as_bytes())
+    }
+}
+
+#[cfg(unix)]
+pub fn mode(candidate: &Candidate) -> Result<String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    Ok(format!(
+        "{:o}",
+        candidate.metadata()?.permissions().mode() & 0o7777
+    ))
+}
+
+#[cfg(not(unix))]
+pub fn mode(candidate: &Candidate) -> Result<String> {
+    let readonly = candidate.metadata()?.permissions().readonly();
+    Ok(if readonly { "444" } else { "666" }.to_string())
+}
+
+#[cfg(unix)]
+fn user(candidate: &Candidate) -> Result<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let uid = candidate.metadata()?.uid();
+    Ok(uzers::get_user_by_uid(uid)
+        .map(|user| user.name().to_string_lossy().into_owned())
+        .unwrap_or_else(|| uid.to_string()))
+}
+
+#[cfg(not(unix))]
+fn user(_candidate: &Candidate) -> Result<String> {
+    Ok("?".to_string())
+}
+
+#[cfg(unix)]
+fn group(candidate: &Candidate) -> Result<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let gid = candidate.metadata()?.gid();
+    Ok(uzers::get_group_by_gid(gid)
+        .map(|group| group.name().to_string_lossy().into_owned())
+        .unwrap_or_else(|| gid.to_string()))
+}
+
+#[cfg(not(unix))]
+fn group(_candidate: &Candidate) -> Result<String> {
+    Ok("?".to_string())
+}
+
+pub fn type_char(candidate: &Candidate) -> char {
+    let file_type = candidate.entry().file_type();
+    if file_type.is_dir() {
+        return 'd';
+    } else if file_type.is_file() {
+        return 'f';
+    } else if file_type.is_symlink() {
+        return 'l';
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+
+        if file_type.is_fifo() {
+            return 'p';
+        } else if file_type.is_socket() {
+            return 's';
+        } else if file_type.is_char_device() {
+            return 'c';
+        } else if file_type.is_block_device() {
+            return 'b';
+        }
+    }
+
+    'U'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Format, Segment};
+
+    #[test]
+    fn test_parse_format() {
+        let format: Format = r"%p\t%s%%\n".parse().unwrap();
+        assert_eq!(
+            format.segments,
+            [
+                Segment::Path,
+                Segment::Literal("\t".to_string()),
+                Segment::Size,
+                Segment::Literal("%\n".to_string()),
+            ]
+        );
+
+        let format: Format = r"[%f] %h %m %u %g %t %y %d\0".parse().unwrap();
+        assert_eq!(format.segments.len(), 17);
+        assert_eq!(format.segments[0], Segment::Literal("[".to_string()));
+        assert_eq!(format.segments[16], Segment::Literal("\0".to_string()));
+
+        let format: Format = r"\q".parse().unwrap();
+        assert_eq!(format.segments, [Segment::Literal(r"\q".to_string())]);
+
+        assert!("%x".parse::<Format>().is_err());
+        assert!("abc %".parse::<Format>().is_err());
+    }
+}
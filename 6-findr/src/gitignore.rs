@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+
+/// Decides which entries below one start path are ignored by `.gitignore`,
+/// `.ignore`, `.git/info/exclude` and the global excludes file, and which are
+/// hidden.
+///
+/// Entries are looked up by their ancestors rather than by the order of the
+/// walk, so this also works when directory contents come first.
+pub struct IgnoreFilter {
+    start: PathBuf,
+    /// The canonical start path, which ignore files are matched against.
+    root: PathBuf,
+    skip_hidden: bool,
+    /// Matchers from outside the walk, lowest precedence first.
+    base: Vec<Gitignore>,
+    /// The ignore files of each directory, relative to `start`.
+    dirs: HashMap<PathBuf, Option<Gitignore>>,
+    /// Whether each directory, relative to `start`, is ignored.
+    ignored_dirs: HashMap<PathBuf, bool>,
+}
+
+impl IgnoreFilter {
+    pub fn new(start: &Path, skip_hidden: bool) -> Self {
+        let root = fs::canonicalize(start).unwrap_or_else(|_| start.to_path_buf());
+        let repo = root.ancestors().find(|dir| dir.join(".git").exists());
+
+        let mut base = vec![];
+        let (global, err) = GitignoreBuilder::new(repo.unwrap_or(&root)).build_global();
+        if let Some(e) = err {
+            eprintln!("{e}");
+        }
+        base.push(global);
+
+        if let Some(repo) = repo {
+            base.extend(load(repo, &[".git/info/exclude"]));
+
+            // Ignore files above the start path, up to the top of the repository.
+            let mut ancestors: Vec<&Path> = root
+                .ancestors()
+                .skip(1)
+                .take_while(|dir| dir.starts_with(repo))
+                .collect();
+            ancestors.reverse();
+            base.extend(
+                ancestors
+                    .into_iter()
+                    .filter_map(|dir| load(dir, &[".gitignore", ".ignore"])),
+            );
+        }
+
+        Self {
+            start: start.to_path_buf(),
+            root,
+            skip_hidden,
+            base,
+            dirs: HashMap::new(),
+            ignored_dirs: HashMap::new(),
+        }
+    }
+
+    /// Whether `path`, found below the start path, or any directory between
+    /// them is ignored. The start path itself never is.
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let path = path.strip_prefix(&self.start).unwrap_or(path);
+        if path.as_os_str().is_empty() {
+            return false;
+        }
+
+        match path.parent() {
+            Some(parent) if self.is_dir_ignored(parent) => true,
+            _ => self.matches(path, is_dir),
+        }
+    }
+
+    fn is_dir_ignored(&mut self, dir: &Path) -> bool {
+        if dir.as_os_str().is_empty() {
+            return false;
+        }
+        if let Some(&ignored) = self.ignored_dirs.get(dir) {
+            return ignored;
+        }
+
+        let ignored = match dir.parent() {
+            Some(parent) if self.is_dir_ignored(parent) => true,
+            _ => self.matches(dir, true),
+        };
+        self.ignored_dirs.insert(dir.to_path_buf(), ignored);
+        ignored
+    }
+
+    /// Checks `path` against the ignore files of its directory first, then
+    /// those further up.
+    fn matches(&mut self, path: &Path, is_dir: bool) -> bool {
+        let is_hidden = path
+            .file_name()
+            .is_some_and(|name| name.as_encoded_bytes().starts_with(b"."));
+        if self.skip_hidden && is_hidden {
+            return true;
+        }
+        // Git's own metadata is never content, hidden files or not.
+        if path.file_name() == Some(OsStr::new(".git")) {
+            return true;
+        }
+
+        let absolute = self.root.join(path);
+        for dir in path.ancestors().skip(1) {
+            if let Some(gitignore) = self.dir_matcher(dir) {
+                match gitignore.matched(&absolute, is_dir) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => {}
+                }
+            }
+        }
+
+        for gitignore in self.base.iter().rev() {
+            match gitignore.matched(&absolute, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+
+        false
+    }
+
+    fn dir_matcher(&mut self, dir: &Path) -> Option<&Gitignore> {
+        if !self.dirs.contains_key(dir) {
+            let gitignore = load(&self.root.join(dir), &[".gitignore", ".ignore"]);
+            self.dirs.insert(dir.to_path_buf(), gitignore);
+        }
+        self.dirs[dir].as_ref()
+    }
+}
+
+/// Builds a matcher from whichever of `files` exist in `dir`, with later
+/// files taking precedence.
+fn load(dir: &Path, files: &[&str]) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new(dir);
+    let mut found = false;
+    for file in files {
+        let path = dir.join(file);
+        if path.is_file() {
+            found = true;
+            if let Some(e) = builder.add(&path) {
+                eprintln!("{e}");
+            }
+        }
+    }
+
+    if !found {
+        return None;
+    }
+
+    match builder.build() {
+        Ok(gitignore) => Some(gitignore),
+        Err(e) => {
+            eprintln!("{e}");
+            None
+        }
+    }
+}
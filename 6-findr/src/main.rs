@@ -1,23 +1,85 @@
-use std::fs;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::SystemTime;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use clap::builder::PossibleValue;
 use clap::{ArgAction, Parser, ValueEnum};
-use regex::Regex;
-use walkdir::WalkDir;
+use globset::GlobMatcher;
+
+use crate::action::Action;
+use crate::duplicates::Duplicates;
+use crate::expr::Expr;
+use crate::gitignore::IgnoreFilter;
+use crate::predicate::{Candidate, Comparison, Predicate, Size};
+use crate::report::Report;
+use crate::walk::WalkOptions;
+
+mod action;
+mod duplicates;
+mod expr;
+mod format;
+mod gitignore;
+#[cfg(target_os = "linux")]
+mod mounts;
+mod predicate;
+mod report;
+mod walk;
+
+const EXPRESSION_HELP: &str = "\
+Expression:
+  Options above may be mixed with a find-style expression, which is ANDed
+  with them. Operators, from highest to lowest precedence:
+    ( EXPR )
+    ! EXPR, -not EXPR
+    EXPR EXPR, EXPR -a EXPR, EXPR -and EXPR
+    EXPR -o EXPR, EXPR -or EXPR
+  Predicates:
+    -name GLOB, -iname GLOB  file name matches a glob with *, ?, [...], {a,b}
+    -path GLOB, -ipath GLOB  whole path matches a glob, where * also matches /
+    -regex REGEX, -iregex REGEX
+                           whole path matches REGEX, anchored at both ends
+    -user NAME|UID, -group NAME|GID, -nouser, -nogroup
+                           owned by a user or group, or by an id without one
+    -perm MODE, -perm -MODE, -perm /MODE
+                           mode bits are exactly MODE, include all of MODE or
+                           any of MODE; octal, or symbolic like u=rw,go+r
+    -links [+-]N           number of hard links
+    -xtype d|f|l[,...]     like -type, for the target of a link that isn't
+                           followed, or for the link itself if it is
+    -fstype TYPE           on a file system of TYPE, such as ext4 or nfs4
+    -type d|f|l[,...], -size [+-]N[cwbkMG], -mtime [+-]N, -mmin [+-]N,
+    -newer FILE, -empty, -true, -false
+  Actions:
+    -prune                 don't descend into a matching directory
+    -print                 print the path; implied if there are no other actions
+    -print0                print the path followed by a NUL
+    -printf FORMAT         print FORMAT, with \n, \t, \0, %% and the directives
+                           %p path, %f name, %h directory, %s size, %m mode,
+                           %u user, %g group, %t mtime, %y type, %d depth
+    -delete                delete the entry; directory contents come first
+    -exec CMD [ARG]... ;   run CMD, with {} replaced by the path
+    -exec CMD [ARG]... {} +
+                           run CMD with as many paths as fit in one command
+    -execdir ..., -okdir ...
+                           like -exec and -ok, run from the entry's directory
+    -ok CMD [ARG]... ;     like -exec, after asking for confirmation";
 
 #[derive(Debug, Parser)]
+#[command(after_help = EXPRESSION_HELP)]
 struct Args {
     #[arg(default_value = ".", help = "Search paths")]
     path: Vec<String>,
     #[arg(
         short,
         long = "name",
-        value_parser = Regex::new,
+        value_name = "GLOB",
+        value_parser = |s: &str| predicate::glob(s, false),
         action = ArgAction::Append,
         num_args = 0..
     )]
-    names: Vec<Regex>,
+    names: Vec<GlobMatcher>,
     #[arg(
         short = 't',
         long = "type",
@@ -28,10 +90,135 @@ struct Args {
         num_args = 0..
     )]
     entry_type: Vec<EntryType>,
+    #[arg(
+        long,
+        value_name = "[+-]N[cwbkMG]",
+        allow_hyphen_values = true,
+        help = "File size, rounded up to units (default: 512-byte blocks)"
+    )]
+    size: Vec<Size>,
+    #[arg(
+        long,
+        value_name = "[+-]N",
+        allow_hyphen_values = true,
+        help = "Last modified N days ago"
+    )]
+    mtime: Vec<Comparison>,
+    #[arg(
+        long,
+        value_name = "[+-]N",
+        allow_hyphen_values = true,
+        help = "Last modified N minutes ago"
+    )]
+    mmin: Vec<Comparison>,
+    #[arg(long, value_name = "FILE", help = "Modified more recently than FILE")]
+    newer: Vec<String>,
+    #[arg(long, help = "Empty regular file or directory")]
+    empty: bool,
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Descend at most N levels below the search paths"
+    )]
+    max_depth: Option<usize>,
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Ignore entries less than N levels deep"
+    )]
+    min_depth: Option<usize>,
+    #[arg(
+        long,
+        help = "Skip entries ignored by .gitignore, .ignore, .git/info/exclude \
+                or the global excludes file, and hidden entries"
+    )]
+    gitignore: bool,
+    #[arg(long, requires = "gitignore", help = "Don't skip hidden entries")]
+    hidden: bool,
+    #[arg(
+        long,
+        value_name = "N",
+        default_value = "1",
+        value_parser = clap::value_parser!(u16).range(1..),
+        help = "Read directories on N threads"
+    )]
+    threads: u16,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "KEY",
+        num_args = 0..=1,
+        default_missing_value = "name",
+        help = "Print entries sorted by KEY; name sorts the entries of each directory"
+    )]
+    sort: Option<SortKey>,
+    #[arg(long, requires = "sort", help = "Reverse the order of --sort")]
+    reverse: bool,
+    #[arg(
+        short = 'P',
+        overrides_with_all = ["follow_args", "follow"],
+        help = "Never follow symlinks (default)"
+    )]
+    no_follow: bool,
+    #[arg(
+        short = 'H',
+        overrides_with_all = ["no_follow", "follow"],
+        help = "Follow symlinks given as search paths"
+    )]
+    follow_args: bool,
+    #[arg(
+        short = 'L',
+        overrides_with_all = ["no_follow", "follow_args"],
+        help = "Follow all symlinks, warning about loops"
+    )]
+    follow: bool,
+    #[arg(
+        long,
+        help = "Don't descend into directories on other file systems [-xdev]"
+    )]
+    one_file_system: bool,
+    #[arg(
+        long,
+        help = "Print groups of regular files with identical contents instead"
+    )]
+    duplicates: bool,
+    #[arg(
+        long,
+        value_name = "SIZE",
+        requires = "duplicates",
+        default_value = "1",
+        value_parser = duplicates::parse_size,
+        help = "Ignore duplicates smaller than SIZE bytes, or with a k, M or G suffix"
+    )]
+    min_size: u64,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Output::Text,
+        help = "Output format of printed entries, with their path, type, size, mtime \
+                and mode, or of --duplicates"
+    )]
+    output: Output,
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone, ValueEnum)]
+pub enum Output {
+    Text,
+    Json,
+    Ndjson,
+    Csv,
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone, ValueEnum)]
+pub enum SortKey {
+    Name,
+    Size,
+    Mtime,
+    Depth,
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
-enum EntryType {
+pub enum EntryType {
     Dir,
     File,
     Link,
@@ -52,44 +239,151 @@ impl ValueEnum for EntryType {
 }
 
 fn main() {
-    let args = Args::parse();
+    let (args, expression) = match expr::split_args(std::env::args_os()) {
+        Ok(split) => split,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
 
-    if let Err(e) = run(args) {
+    if let Err(e) = run(Args::parse_from(args), &expression) {
         eprintln!("{e}");
         std::process::exit(1);
     }
 }
 
-fn run(args: Args) -> Result<()> {
-    let name_filter =
-        |name: &str| args.names.is_empty() || args.names.iter().any(|re| re.is_match(name));
-    let type_filter = |file_type: fs::FileType| {
-        args.entry_type.is_empty()
-            || args.entry_type.iter().any(|t| match t {
-                EntryType::Dir => file_type.is_dir(),
-                EntryType::File => file_type.is_file(),
-                EntryType::Link => file_type.is_symlink(),
-            })
-    };
+fn run(args: Args, expression: &[String]) -> Result<()> {
+    // Plain -print does for text output in walk order.
+    let report = (!args.duplicates
+        && (args.output != Output::Text
+            || args.reverse
+            || args.sort.is_some_and(|key| key != SortKey::Name)))
+    .then(|| {
+        Rc::new(Report::new(
+            args.output,
+            args.sort.unwrap_or(SortKey::Name),
+            args.reverse,
+        ))
+    });
+    let filter = filter(&args, expression, report.as_ref())?;
+    if report.is_some()
+        && filter.any_action(&|action| matches!(action, Action::Print0 | Action::Printf(_)))
+    {
+        bail!("--output, --reverse and --sort by anything but name only apply to -print, not -print0 or -printf");
+    }
+    // Nothing is reported without a -print, not even an empty JSON array.
+    let report =
+        report.filter(|_| filter.any_action(&|action| matches!(action, Action::Report(_))));
+    let contents_first = filter.any_action(&|action| matches!(action, Action::Delete(_)));
+    let mut duplicates = args.duplicates.then(|| Duplicates::new(args.min_size));
 
     for path in args.path {
-        for entry in WalkDir::new(path) {
-            match entry {
-                Ok(entry) => {
-                    if !name_filter(&entry.file_name().to_string_lossy()) {
-                        continue;
-                    }
+        let mut ignore_filter = args
+            .gitignore
+            .then(|| IgnoreFilter::new(Path::new(&path), !args.hidden));
 
-                    if !type_filter(entry.file_type()) {
-                        continue;
-                    }
+        let options = WalkOptions {
+            max_depth: args.max_depth.unwrap_or(usize::MAX),
+            contents_first,
+            sort: args.sort.is_some(),
+            threads: args.threads.into(),
+            follow_links: args.follow,
+            follow_root_links: args.follow || args.follow_args,
+            same_file_system: args.one_file_system,
+        };
+        let min_depth = args.min_depth.unwrap_or(0);
 
-                    println!("{}", entry.path().display());
+        walk::walk(Path::new(&path), &options, &mut |entry, depth| {
+            if let Some(ignore_filter) = &mut ignore_filter {
+                if ignore_filter.is_ignored(entry.path(), entry.file_type().is_dir()) {
+                    return false;
                 }
+            }
+            if depth < min_depth {
+                return true;
+            }
+
+            let candidate = Candidate::new(entry, depth);
+            match filter.is_match(&candidate) {
+                Ok(true) => {
+                    if let Some(duplicates) = &mut duplicates {
+                        if entry.file_type().is_file() {
+                            match candidate.metadata() {
+                                Ok(metadata) => duplicates.add(entry.path(), metadata),
+                                Err(e) => eprintln!("{e}"),
+                            }
+                        }
+                    }
+                }
+                Ok(false) => {}
                 Err(e) => eprintln!("{e}"),
             }
-        }
+            !candidate.is_pruned()
+        });
+    }
+
+    if let Some(duplicates) = duplicates {
+        duplicates::write(&mut io::stdout().lock(), &duplicates.groups(), args.output)?;
+    }
+    let result = filter.finish();
+    if let Some(report) = report {
+        report.finish()?;
+    }
+    result
+}
+
+/// Builds the filter from the command-line options, ANDed together, followed
+/// by the parsed `expression`. `-print` goes through `report` if there is one.
+fn filter(args: &Args, expression: &[String], report: Option<&Rc<Report>>) -> Result<Expr> {
+    let now = SystemTime::now();
+    let mut exprs = vec![];
+
+    if !args.names.is_empty() {
+        exprs.push(Expr::any(
+            args.names
+                .iter()
+                .cloned()
+                .map(|glob| Expr::Predicate(Predicate::Name(glob))),
+        ));
+    }
+    if !args.entry_type.is_empty() {
+        exprs.push(Expr::Predicate(Predicate::Type(args.entry_type.clone())));
+    }
+    exprs.extend(
+        args.size
+            .iter()
+            .map(|&size| Expr::Predicate(Predicate::Size(size))),
+    );
+    exprs.extend(
+        args.mtime
+            .iter()
+            .map(|&comparison| Expr::Predicate(Predicate::MTime { comparison, now })),
+    );
+    exprs.extend(
+        args.mmin
+            .iter()
+            .map(|&comparison| Expr::Predicate(Predicate::MMin { comparison, now })),
+    );
+    for file in &args.newer {
+        exprs.push(Expr::Predicate(Predicate::Newer(expr::newer(file)?)));
+    }
+    if args.empty {
+        exprs.push(Expr::Predicate(Predicate::Empty));
+    }
+    let expr = Expr::parse(expression, now)?;
+    let has_action = expr.has_action();
+    if has_action && args.duplicates {
+        bail!("--duplicates can't be combined with actions");
+    }
+    exprs.push(expr);
+    if !has_action && !args.duplicates {
+        exprs.push(Expr::Action(Action::Print));
     }
 
-    Ok(())
+    let filter = Expr::all(exprs);
+    Ok(match report {
+        Some(report) => filter.replace_print(&|| Action::Report(Rc::clone(report))),
+        None => filter,
+    })
 }
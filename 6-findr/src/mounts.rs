@@ -0,0 +1,54 @@
+use std::collections::HashSet;
+use std::fs;
+
+use anyhow::{anyhow, Result};
+
+const MOUNTINFO: &str = "/proc/self/mountinfo";
+
+/// Device numbers of the mounted file systems of type `fstype`.
+pub fn devices(fstype: &str) -> Result<HashSet<u64>> {
+    let mountinfo = fs::read_to_string(MOUNTINFO).map_err(|e| anyhow!("{MOUNTINFO}: {e}"))?;
+    Ok(parse(&mountinfo)
+        .filter(|&(_, t)| t == fstype)
+        .map(|(device, _)| device)
+        .collect())
+}
+
+/// The device number and file system type of each mount in `mountinfo`.
+fn parse(mountinfo: &str) -> impl Iterator<Item = (u64, &str)> {
+    mountinfo.lines().filter_map(|line| {
+        let mut fields = line.split(' ');
+        let (major, minor) = fields.nth(2)?.split_once(':')?;
+        // Optional fields come before the separator, so the type is
+        // found after it.
+        let fstype = fields.skip_while(|&field| field != "-").nth(1)?;
+        Some((
+            libc::makedev(major.parse().ok()?, minor.parse().ok()?),
+            fstype,
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn test_parse() {
+        let mountinfo = "\
+22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw
+23 22 0:22 / /proc rw,nosuid shared:12 master:1 - proc proc rw
+24 22 0:45 / /home/me rw - nfs4 server:/home rw,vers=4.2
+bad line
+";
+        let mounts: Vec<_> = parse(mountinfo).collect();
+        assert_eq!(
+            mounts,
+            [
+                (libc::makedev(8, 1), "ext4"),
+                (libc::makedev(0, 22), "proc"),
+                (libc::makedev(0, 45), "nfs4"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,500 @@
+use std::cell::{Cell, OnceCell};
+#[cfg(target_os = "linux")]
+use std::collections::HashSet;
+use std::fs;
+use std::fs::{FileType, Metadata};
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, bail, Error, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
+use walkdir::DirEntry;
+
+use crate::EntryType;
+
+/// A walked entry whose metadata is only read once a predicate asks for it.
+pub struct Candidate<'a> {
+    entry: &'a DirEntry,
+    depth: usize,
+    metadata: OnceCell<Metadata>,
+    pruned: Cell<bool>,
+}
+
+impl<'a> Candidate<'a> {
+    /// `depth` is below the start path, which the parallel walk tracks itself
+    /// rather than relying on `DirEntry::depth`.
+    pub fn new(entry: &'a DirEntry, depth: usize) -> Self {
+        Self {
+            entry,
+            depth,
+            metadata: OnceCell::new(),
+            pruned: Cell::new(false),
+        }
+    }
+
+    /// Whether `-prune` matched, so the walk shouldn't descend into this entry.
+    pub fn is_pruned(&self) -> bool {
+        self.pruned.get()
+    }
+
+    pub fn entry(&self) -> &DirEntry {
+        self.entry
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn metadata(&self) -> Result<&Metadata> {
+        if let Some(metadata) = self.metadata.get() {
+            return Ok(metadata);
+        }
+
+        let metadata = self
+            .entry
+            .metadata()
+            .map_err(|e| anyhow!("{}: {e}", self.entry.path().display()))?;
+        Ok(self.metadata.get_or_init(|| metadata))
+    }
+}
+
+/// A numeric argument in the `+N` (more than), `-N` (less than) or `N`
+/// (exactly) form.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Comparison {
+    Less(u64),
+    Exactly(u64),
+    Greater(u64),
+}
+
+impl Comparison {
+    pub fn matches(&self, n: u64) -> bool {
+        match *self {
+            Self::Less(value) => n < value,
+            Self::Exactly(value) => n == value,
+            Self::Greater(value) => n > value,
+        }
+    }
+}
+
+impl FromStr for Comparison {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (constructor, digits): (fn(u64) -> Self, _) = match s.as_bytes().first() {
+            Some(b'+') => (Self::Greater, &s[1..]),
+            Some(b'-') => (Self::Less, &s[1..]),
+            _ => (Self::Exactly, s),
+        };
+
+        if !digits.bytes().all(|b| b.is_ascii_digit()) {
+            bail!("invalid number \"{s}\"");
+        }
+
+        digits
+            .parse()
+            .map(constructor)
+            .map_err(|_| anyhow!("invalid number \"{s}\""))
+    }
+}
+
+/// A `-size` argument. Sizes are rounded up to whole units before comparing,
+/// so `-1M` only matches empty files, as in GNU find.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Size {
+    comparison: Comparison,
+    unit: u64,
+}
+
+impl Size {
+    pub fn matches(&self, len: u64) -> bool {
+        self.comparison.matches(len.div_ceil(self.unit))
+    }
+}
+
+impl FromStr for Size {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (number, unit) = match s.char_indices().last() {
+            Some((i, c)) if c.is_ascii_alphabetic() => (&s[..i], c),
+            _ => (s, 'b'),
+        };
+
+        let unit = match unit {
+            'c' => 1,
+            'w' => 2,
+            'b' => 512,
+            'k' => 1024,
+            'M' => 1024 * 1024,
+            'G' => 1024 * 1024 * 1024,
+            _ => bail!("invalid size unit \"{unit}\" in \"{s}\""),
+        };
+
+        Ok(Self {
+            comparison: number.parse()?,
+            unit,
+        })
+    }
+}
+
+/// How a `-perm` mode is compared with an entry's mode.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+enum PermMatch {
+    /// `MODE`: exactly these bits are set.
+    Exactly,
+    /// `-MODE`: at least all of these bits are set.
+    All,
+    /// `/MODE`: any of these bits are set.
+    Any,
+}
+
+/// A `-perm` argument, in octal or symbolic (`u=rw,go+r`) form. Symbolic
+/// modes start from no bits set.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Perm {
+    matching: PermMatch,
+    bits: u32,
+}
+
+impl Perm {
+    pub fn matches(&self, mode: u32) -> bool {
+        let mode = mode & 0o7777;
+        match self.matching {
+            PermMatch::Exactly => mode == self.bits,
+            PermMatch::All => mode & self.bits == self.bits,
+            // As in GNU find, no bits at all match every mode.
+            PermMatch::Any => self.bits == 0 || mode & self.bits != 0,
+        }
+    }
+}
+
+impl FromStr for Perm {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (matching, mode) = match s.as_bytes().first() {
+            Some(b'-') => (PermMatch::All, &s[1..]),
+            Some(b'/') => (PermMatch::Any, &s[1..]),
+            _ => (PermMatch::Exactly, s),
+        };
+
+        let bits = if !mode.is_empty() && mode.bytes().all(|b| matches!(b, b'0'..=b'7')) {
+            u32::from_str_radix(mode, 8)
+                .ok()
+                .filter(|&bits| bits <= 0o7777)
+                .ok_or_else(|| anyhow!("invalid mode \"{s}\""))?
+        } else {
+            symbolic_mode(mode).ok_or_else(|| anyhow!("invalid mode \"{s}\""))?
+        };
+
+        Ok(Self { matching, bits })
+    }
+}
+
+/// Applies comma-separated `[ugoa]*([-+=]([rwxXst]*|[ugo]))+` clauses to a
+/// mode with no bits set.
+fn symbolic_mode(s: &str) -> Option<u32> {
+    let mut mode = 0;
+
+    for clause in s.split(',') {
+        let mut chars = clause.chars().peekable();
+
+        let mut who = 0;
+        while let Some(bits) = chars.peek().and_then(|c| match c {
+            'u' => Some(0o4700),
+            'g' => Some(0o2070),
+            'o' => Some(0o1007),
+            'a' => Some(0o7777),
+            _ => None,
+        }) {
+            who |= bits;
+            chars.next();
+        }
+        if who == 0 {
+            who = 0o7777;
+        }
+
+        let mut has_action = false;
+        while let Some(op) = chars.next() {
+            if !matches!(op, '+' | '-' | '=') {
+                return None;
+            }
+            has_action = true;
+
+            let mut bits = 0;
+            let copy_shift = chars.peek().and_then(|c| match c {
+                'u' => Some(6),
+                'g' => Some(3),
+                'o' => Some(0),
+                _ => None,
+            });
+            if let Some(shift) = copy_shift {
+                chars.next();
+                bits = ((mode >> shift) & 0o7) * 0o111;
+            } else {
+                while let Some(perm) = chars.peek().and_then(|c| match c {
+                    'r' => Some(0o444),
+                    'w' => Some(0o222),
+                    'x' | 'X' => Some(0o111),
+                    's' => Some(0o6000),
+                    't' => Some(0o1000),
+                    _ => None,
+                }) {
+                    bits |= perm;
+                    chars.next();
+                }
+            }
+
+            let bits = bits & who;
+            match op {
+                '+' => mode |= bits,
+                '-' => mode &= !bits,
+                _ => mode = (mode & !who) | bits,
+            }
+        }
+
+        if !has_action {
+            return None;
+        }
+    }
+
+    Some(mode)
+}
+
+#[derive(Debug, Clone)]
+pub enum Predicate {
+    True,
+    False,
+    /// Glob matched against the file name.
+    Name(GlobMatcher),
+    /// Glob matched against the whole path, where `*` also matches `/`.
+    Path(GlobMatcher),
+    /// Regex matched against the whole path, anchored at both ends.
+    Regex(Regex),
+    Type(Vec<EntryType>),
+    /// The type of a link's target, or of the link itself if it's followed
+    /// or broken.
+    XType(Vec<EntryType>),
+    Size(Size),
+    /// Age of the last modification in whole days, relative to `now`.
+    MTime {
+        comparison: Comparison,
+        now: SystemTime,
+    },
+    /// Age of the last modification in whole minutes, relative to `now`.
+    MMin {
+        comparison: Comparison,
+        now: SystemTime,
+    },
+    Newer(SystemTime),
+    Empty,
+    /// Always true; stops the walk from descending into a matched directory.
+    Prune,
+    #[cfg(unix)]
+    User(u32),
+    #[cfg(unix)]
+    Group(u32),
+    /// Owned by a user id with no user.
+    #[cfg(unix)]
+    NoUser,
+    /// Owned by a group id with no group.
+    #[cfg(unix)]
+    NoGroup,
+    #[cfg(unix)]
+    Perm(Perm),
+    /// Number of hard links.
+    #[cfg(unix)]
+    Links(Comparison),
+    /// On one of these devices.
+    #[cfg(target_os = "linux")]
+    FsType(HashSet<u64>),
+}
+
+impl Predicate {
+    pub fn is_match(&self, candidate: &Candidate) -> Result<bool> {
+        match self {
+            Self::True => Ok(true),
+            Self::False => Ok(false),
+            Self::Name(glob) => Ok(glob.is_match(candidate.entry().file_name())),
+            Self::Path(glob) => Ok(glob.is_match(candidate.entry().path())),
+            Self::Regex(re) => Ok(re.is_match(&candidate.entry().path().to_string_lossy())),
+            Self::Type(entry_types) => Ok(is_type(entry_types, candidate.entry().file_type())),
+            Self::XType(entry_types) => {
+                let entry = candidate.entry();
+                let file_type = if entry.file_type().is_symlink() {
+                    fs::metadata(entry.path()).map_or(entry.file_type(), |m| m.file_type())
+                } else if entry.path_is_symlink() {
+                    fs::symlink_metadata(entry.path())
+                        .map_err(|e| anyhow!("{}: {e}", entry.path().display()))?
+                        .file_type()
+                } else {
+                    entry.file_type()
+                };
+                Ok(is_type(entry_types, file_type))
+            }
+            Self::Size(size) => Ok(size.matches(candidate.metadata()?.len())),
+            Self::MTime { comparison, now } => {
+                let age = age(*now, candidate.metadata()?.modified()?);
+                Ok(comparison.matches(age.as_secs() / (24 * 60 * 60)))
+            }
+            Self::MMin { comparison, now } => {
+                let age = age(*now, candidate.metadata()?.modified()?);
+                Ok(comparison.matches(age.as_secs() / 60))
+            }
+            Self::Newer(time) => Ok(candidate.metadata()?.modified()? > *time),
+            Self::Empty => {
+                let file_type = candidate.entry().file_type();
+                if file_type.is_dir() {
+                    let path = candidate.entry().path();
+                    let mut entries =
+                        fs::read_dir(path).map_err(|e| anyhow!("{}: {e}", path.display()))?;
+                    Ok(entries.next().is_none())
+                } else if file_type.is_file() {
+                    Ok(candidate.metadata()?.len() == 0)
+                } else {
+                    Ok(false)
+                }
+            }
+            Self::Prune => {
+                candidate.pruned.set(true);
+                Ok(true)
+            }
+            #[cfg(unix)]
+            Self::User(uid) => Ok(candidate.metadata()?.uid() == *uid),
+            #[cfg(unix)]
+            Self::Group(gid) => Ok(candidate.metadata()?.gid() == *gid),
+            #[cfg(unix)]
+            Self::NoUser => Ok(uzers::get_user_by_uid(candidate.metadata()?.uid()).is_none()),
+            #[cfg(unix)]
+            Self::NoGroup => Ok(uzers::get_group_by_gid(candidate.metadata()?.gid()).is_none()),
+            #[cfg(unix)]
+            Self::Perm(perm) => Ok(perm.matches(candidate.metadata()?.mode())),
+            #[cfg(unix)]
+            Self::Links(comparison) => Ok(comparison.matches(candidate.metadata()?.nlink())),
+            #[cfg(target_os = "linux")]
+            Self::FsType(devices) => Ok(devices.contains(&candidate.metadata()?.dev())),
+        }
+    }
+}
+
+/// Builds a matcher for a shell glob with `*`, `?`, `[...]` and `{a,b}`.
+pub fn glob(pattern: &str, case_insensitive: bool) -> Result<GlobMatcher, globset::Error> {
+    GlobBuilder::new(pattern)
+        .case_insensitive(case_insensitive)
+        .backslash_escape(true)
+        .build()
+        .map(|glob| glob.compile_matcher())
+}
+
+fn is_type(entry_types: &[EntryType], file_type: FileType) -> bool {
+    entry_types.iter().any(|t| match t {
+        EntryType::Dir => file_type.is_dir(),
+        EntryType::File => file_type.is_file(),
+        EntryType::Link => file_type.is_symlink(),
+    })
+}
+
+fn age(now: SystemTime, time: SystemTime) -> Duration {
+    // Files from the future count as brand new.
+    now.duration_since(time).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glob, Comparison, Perm, Size};
+
+    #[test]
+    fn test_parse_comparison() {
+        assert_eq!("3".parse::<Comparison>().unwrap(), Comparison::Exactly(3));
+        assert_eq!("+3".parse::<Comparison>().unwrap(), Comparison::Greater(3));
+        assert_eq!("-3".parse::<Comparison>().unwrap(), Comparison::Less(3));
+
+        assert!("".parse::<Comparison>().is_err());
+        assert!("+".parse::<Comparison>().is_err());
+        assert!("+-3".parse::<Comparison>().is_err());
+        assert!("3d".parse::<Comparison>().is_err());
+    }
+
+    #[test]
+    fn test_glob() {
+        let matcher = glob("*.{rs,toml}", false).unwrap();
+        assert!(matcher.is_match("main.rs"));
+        assert!(matcher.is_match("Cargo.toml"));
+        assert!(!matcher.is_match("parsers.json"));
+        assert!(!matcher.is_match("main.RS"));
+
+        let matcher = glob("[a-c]?.txt", true).unwrap();
+        assert!(matcher.is_match("b1.txt"));
+        assert!(matcher.is_match("C2.TXT"));
+        assert!(!matcher.is_match("d1.txt"));
+        assert!(!matcher.is_match("b.txt"));
+
+        let matcher = glob(r"\*", false).unwrap();
+        assert!(matcher.is_match("*"));
+        assert!(!matcher.is_match("a"));
+
+        assert!(glob("[a", false).is_err());
+        assert!(glob("{a", false).is_err());
+    }
+
+    #[test]
+    fn test_parse_perm() {
+        let perm: Perm = "644".parse().unwrap();
+        assert!(perm.matches(0o100644));
+        assert!(!perm.matches(0o100664));
+
+        let perm: Perm = "-o+w".parse().unwrap();
+        assert!(perm.matches(0o666));
+        assert!(perm.matches(0o1777));
+        assert!(!perm.matches(0o664));
+
+        let perm: Perm = "/u=x,g=x".parse().unwrap();
+        assert!(perm.matches(0o100));
+        assert!(perm.matches(0o010));
+        assert!(!perm.matches(0o001));
+
+        let perm: Perm = "/000".parse().unwrap();
+        assert!(perm.matches(0));
+
+        assert_eq!("u=rw,go=r".parse::<Perm>().unwrap(), "644".parse().unwrap());
+        assert_eq!("a=r,u+w".parse::<Perm>().unwrap(), "644".parse().unwrap());
+        assert_eq!("=rx,u+w".parse::<Perm>().unwrap(), "755".parse().unwrap());
+        assert_eq!(
+            "u=rwx,g=u-w,o=g".parse::<Perm>().unwrap(),
+            "755".parse().unwrap()
+        );
+        assert_eq!("u+s,+t".parse::<Perm>().unwrap(), "5000".parse().unwrap());
+        assert_eq!("a+rwx,o-w".parse::<Perm>().unwrap(), "775".parse().unwrap());
+
+        for bad in ["", "-", "8", "17777", "u", "u+q", "u+w,", "z=r", "u+ro"] {
+            assert!(bad.parse::<Perm>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_parse_size() {
+        let size: Size = "+10M".parse().unwrap();
+        assert!(!size.matches(10 * 1024 * 1024));
+        assert!(size.matches(10 * 1024 * 1024 + 1));
+
+        let size: Size = "-1k".parse().unwrap();
+        assert!(size.matches(0));
+        assert!(!size.matches(1));
+
+        let size: Size = "2c".parse().unwrap();
+        assert!(size.matches(2));
+        assert!(!size.matches(3));
+
+        let size: Size = "1".parse().unwrap();
+        assert!(size.matches(1));
+        assert!(size.matches(512));
+        assert!(!size.matches(513));
+
+        assert!("10x".parse::<Size>().is_err());
+        assert!("M".parse::<Size>().is_err());
+    }
+}
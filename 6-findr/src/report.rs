@@ -0,0 +1,183 @@
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use anyhow::Result;
+use chrono::{DateTime, Local, SecondsFormat};
+use serde_json::{json, Value};
+
+use crate::format::{mode, type_char, write_os_str};
+use crate::predicate::Candidate;
+use crate::{Output, SortKey};
+
+/// Prints the entries matched by `-print` in an `--output` format, holding
+/// them back until the walk is over if they need sorting.
+#[derive(Debug)]
+pub struct Report {
+    output: Output,
+    sort: SortKey,
+    reverse: bool,
+    /// Entries held back for sorting.
+    records: RefCell<Vec<Record>>,
+    /// Number of entries written so far.
+    written: Cell<usize>,
+}
+
+#[derive(Debug)]
+struct Record {
+    path: PathBuf,
+    file_type: char,
+    size: u64,
+    mtime: SystemTime,
+    mode: String,
+    depth: usize,
+}
+
+impl Record {
+    fn new(candidate: &Candidate) -> Result<Self> {
+        let metadata = candidate.metadata()?;
+        Ok(Self {
+            path: candidate.entry().path().to_path_buf(),
+            file_type: type_char(candidate),
+            size: metadata.len(),
+            mtime: metadata.modified()?,
+            mode: mode(candidate)?,
+            depth: candidate.depth(),
+        })
+    }
+
+    fn mtime(&self) -> String {
+        DateTime::<Local>::from(self.mtime).to_rfc3339_opts(SecondsFormat::Secs, false)
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "path": self.path.to_string_lossy(),
+            "type": self.file_type.to_string(),
+            "size": self.size,
+            "mtime": self.mtime(),
+            "mode": self.mode,
+        })
+    }
+}
+
+impl Report {
+    /// `sort` is the order of the walk, and whether to reverse it. Sorting
+    /// by name needs nothing more, since the walk is already in name order.
+    pub fn new(output: Output, sort: SortKey, reverse: bool) -> Self {
+        Self {
+            output,
+            sort,
+            reverse,
+            records: RefCell::new(vec![]),
+            written: Cell::new(0),
+        }
+    }
+
+    fn is_buffered(&self) -> bool {
+        self.reverse || self.sort != SortKey::Name
+    }
+
+    pub fn add(&self, candidate: &Candidate) -> Result<()> {
+        let record = Record::new(candidate)?;
+        if self.is_buffered() {
+            self.records.borrow_mut().push(record);
+            Ok(())
+        } else {
+            self.write(&mut io::stdout().lock(), &record)
+        }
+    }
+
+    /// Writes the entries held back, and closes the output.
+    pub fn finish(&self) -> Result<()> {
+        let mut records = self.records.take();
+        // The walk is in name order, and stable sorts keep ties in it, even
+        // when the key is reversed.
+        let compare = |a: &Record, b: &Record| match self.sort {
+            SortKey::Name => Ordering::Equal,
+            SortKey::Size => a.size.cmp(&b.size),
+            SortKey::Mtime => a.mtime.cmp(&b.mtime),
+            SortKey::Depth => a.depth.cmp(&b.depth),
+        };
+        match (&self.sort, self.reverse) {
+            (SortKey::Name, true) => records.reverse(),
+            (_, true) => records.sort_by(|a, b| compare(b, a)),
+            (_, false) => records.sort_by(compare),
+        }
+
+        let mut out = io::stdout().lock();
+        for record in &records {
+            self.write(&mut out, record)?;
+        }
+
+        let empty = self.written.get() == 0;
+        match self.output {
+            Output::Json if empty => writeln!(out, "[]")?,
+            Output::Json => writeln!(out, "\n]")?,
+            Output::Csv if empty => writeln!(out, "{CSV_HEADER}")?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn write(&self, out: &mut impl Write, record: &Record) -> Result<()> {
+        let first = self.written.get() == 0;
+        self.written.set(self.written.get() + 1);
+
+        match self.output {
+            Output::Text => {
+                write_os_str(out, record.path.as_os_str())?;
+                writeln!(out)?;
+            }
+            Output::Json => {
+                out.write_all(if first { b"[\n" } else { b",\n" })?;
+                serde_json::to_writer(&mut *out, &record.to_json())?;
+            }
+            Output::Ndjson => {
+                serde_json::to_writer(&mut *out, &record.to_json())?;
+                writeln!(out)?;
+            }
+            Output::Csv => {
+                if first {
+                    writeln!(out, "{CSV_HEADER}")?;
+                }
+                writeln!(
+                    out,
+                    "{},{},{},{},{}",
+                    csv_field(&record.path.to_string_lossy()),
+                    record.file_type,
+                    record.size,
+                    record.mtime(),
+                    record.mode
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+const CSV_HEADER: &str = "path,type,size,mtime,mode";
+
+/// Quotes `s` if it contains a comma, a quote or a line break.
+pub fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::csv_field;
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("a/b.txt"), "a/b.txt");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+}
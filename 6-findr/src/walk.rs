@@ -0,0 +1,479 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::{self, Thread};
+use std::time::Duration;
+use std::{fs, io, iter};
+
+use crossbeam_deque::{Injector, Stealer, Worker};
+use walkdir::{DirEntry, WalkDir};
+
+#[derive(Debug, Clone)]
+pub struct WalkOptions {
+    pub max_depth: usize,
+    /// Visit the contents of a directory before the directory itself.
+    pub contents_first: bool,
+    /// Visit the entries of each directory in file name order.
+    pub sort: bool,
+    /// Number of threads reading directories.
+    pub threads: usize,
+    /// Follow symlinks below the start path.
+    pub follow_links: bool,
+    /// Follow the start path if it's a symlink.
+    pub follow_root_links: bool,
+    /// Don't descend into directories on other file systems.
+    pub same_file_system: bool,
+}
+
+/// Walks `root`, calling `visit` with every entry and its depth below `root`.
+/// `visit` returns whether to descend into the entry if it's a directory.
+/// Errors are reported on stderr.
+///
+/// With more than one thread, directories are read concurrently but `visit`
+/// is still only called from the current thread.
+pub fn walk(root: &Path, options: &WalkOptions, visit: &mut dyn FnMut(&DirEntry, usize) -> bool) {
+    // Contents can't reliably come first when directories are read in
+    // parallel, and only unix devices are compared to stay on one file system.
+    if options.threads > 1 && !options.contents_first && (cfg!(unix) || !options.same_file_system) {
+        walk_parallel(root, options, visit);
+    } else {
+        walk_sequential(root, options, visit);
+    }
+}
+
+fn walk_sequential(
+    root: &Path,
+    options: &WalkOptions,
+    visit: &mut dyn FnMut(&DirEntry, usize) -> bool,
+) {
+    let mut walker = WalkDir::new(root)
+        .max_depth(options.max_depth)
+        .contents_first(options.contents_first)
+        .follow_links(options.follow_links)
+        .follow_root_links(options.follow_root_links)
+        .same_file_system(options.same_file_system);
+    if options.sort {
+        walker = walker.sort_by_file_name();
+    }
+
+    let mut entries = walker.into_iter();
+    while let Some(entry) = entries.next() {
+        match entry {
+            Ok(entry) => {
+                let descend = visit(&entry, entry.depth());
+                // Skipping only works before a directory's contents are visited.
+                if !descend && entry.file_type().is_dir() && !options.contents_first {
+                    entries.skip_current_dir();
+                }
+            }
+            Err(e) => match broken_link(&e) {
+                Some(entry) => {
+                    visit(&entry, e.depth());
+                }
+                None => report(&e),
+            },
+        }
+    }
+}
+
+/// When following links, walkdir reports broken links as errors, while find
+/// treats them as links.
+fn broken_link(e: &walkdir::Error) -> Option<DirEntry> {
+    if e.io_error()?.kind() != io::ErrorKind::NotFound {
+        return None;
+    }
+
+    WalkDir::new(e.path()?)
+        .max_depth(0)
+        .follow_root_links(false)
+        .into_iter()
+        .next()?
+        .ok()
+        .filter(|entry| entry.file_type().is_symlink())
+}
+
+fn report(e: &walkdir::Error) {
+    if e.loop_ancestor().is_some() {
+        eprintln!("warning: {e}");
+    } else {
+        eprintln!("{e}");
+    }
+}
+
+/// An entry that can't be visited.
+enum Skipped {
+    Error(walkdir::Error),
+    /// A followed link to one of the directories above it.
+    Loop {
+        path: PathBuf,
+        ancestor: PathBuf,
+    },
+}
+
+impl Skipped {
+    fn report(&self) {
+        match self {
+            Self::Error(e) => report(e),
+            Self::Loop { path, ancestor } => eprintln!(
+                "warning: File system loop found: {} points to an ancestor {}",
+                path.display(),
+                ancestor.display()
+            ),
+        }
+    }
+}
+
+/// When following links, the paths of a directory and the directories above
+/// it, each along with its canonical path.
+type Ancestors = Arc<Vec<(PathBuf, PathBuf)>>;
+
+/// A directory to read, and the depth of its entries.
+struct Job {
+    dir: PathBuf,
+    depth: usize,
+    /// The directories above `dir`.
+    ancestors: Ancestors,
+}
+
+struct Listing {
+    dir: PathBuf,
+    depth: usize,
+    /// `dir` and the directories above it.
+    ancestors: Ancestors,
+    entries: Vec<Result<DirEntry, Skipped>>,
+}
+
+/// Reads directories on a pool of threads sharing a work-stealing queue,
+/// while the calling thread decides what to descend into.
+fn walk_parallel(
+    root: &Path,
+    options: &WalkOptions,
+    visit: &mut dyn FnMut(&DirEntry, usize) -> bool,
+) {
+    let root = WalkDir::new(root)
+        .max_depth(0)
+        .follow_root_links(options.follow_root_links)
+        .into_iter()
+        .next();
+    let root = match root {
+        Some(Ok(root)) => root,
+        Some(Err(e)) => {
+            match broken_link(&e) {
+                Some(entry) => {
+                    visit(&entry, 0);
+                }
+                None => report(&e),
+            }
+            return;
+        }
+        None => return,
+    };
+    // Unless all links are followed, a followed start path is still a link.
+    let is_dir = root.file_type().is_dir() || options.follow_root_links && root.path().is_dir();
+    if !visit(&root, 0) || !is_dir || options.max_depth == 0 {
+        return;
+    }
+
+    let root_device = if options.same_file_system {
+        match device(root.path()) {
+            Ok(device) => Some(device),
+            Err(e) => {
+                eprintln!("{}: {e}", root.path().display());
+                return;
+            }
+        }
+    } else {
+        None
+    };
+
+    let injector = Injector::new();
+    let done = AtomicBool::new(false);
+    let (tx, rx) = mpsc::channel();
+    let workers: Vec<_> = (0..options.threads).map(|_| Worker::new_fifo()).collect();
+    let stealers: Vec<_> = workers.iter().map(Worker::stealer).collect();
+
+    thread::scope(|scope| {
+        let threads: Vec<_> = workers
+            .into_iter()
+            .map(|worker| {
+                let tx = tx.clone();
+                let (injector, stealers, done) = (&injector, &stealers, &done);
+                let handle = scope.spawn(move || {
+                    read_dirs(&worker, injector, stealers, done, options, root_device, tx)
+                });
+                handle.thread().clone()
+            })
+            .collect();
+        drop(tx);
+
+        let mut walk = ParallelWalk {
+            options,
+            injector: &injector,
+            threads: &threads,
+            submitted: 0,
+            pending: 0,
+        };
+        walk.submit(root.path().to_path_buf(), 1, Arc::default());
+        if options.sort {
+            SortedVisit::default().visit_dir(&mut walk, &rx, root.path(), visit);
+        } else {
+            walk.visit_unsorted(&rx, visit);
+        }
+        done.store(true, Ordering::Release);
+        threads.iter().for_each(Thread::unpark);
+    });
+}
+
+struct ParallelWalk<'a> {
+    options: &'a WalkOptions,
+    injector: &'a Injector<Job>,
+    /// The threads reading directories, woken in turn as jobs are queued.
+    threads: &'a [Thread],
+    submitted: usize,
+    /// Directories queued but not yet received.
+    pending: usize,
+}
+
+impl ParallelWalk<'_> {
+    fn submit(&mut self, dir: PathBuf, depth: usize, ancestors: Ancestors) {
+        self.pending += 1;
+        self.injector.push(Job {
+            dir,
+            depth,
+            ancestors,
+        });
+        self.submitted += 1;
+        self.threads[self.submitted % self.threads.len()].unpark();
+    }
+
+    fn receive(&mut self, rx: &mpsc::Receiver<Listing>) -> Option<Listing> {
+        let listing = rx.recv().ok()?;
+        self.pending -= 1;
+        Some(listing)
+    }
+
+    fn can_descend(&self, entry: &DirEntry, depth: usize) -> bool {
+        entry.file_type().is_dir() && depth < self.options.max_depth
+    }
+
+    /// Visits listings as they come in.
+    fn visit_unsorted(
+        &mut self,
+        rx: &mpsc::Receiver<Listing>,
+        visit: &mut dyn FnMut(&DirEntry, usize) -> bool,
+    ) {
+        while self.pending > 0 {
+            let Some(listing) = self.receive(rx) else {
+                return;
+            };
+
+            for entry in listing.entries {
+                match entry {
+                    Ok(entry) => {
+                        if visit(&entry, listing.depth) && self.can_descend(&entry, listing.depth) {
+                            let ancestors = Arc::clone(&listing.ancestors);
+                            self.submit(entry.into_path(), listing.depth + 1, ancestors);
+                        }
+                    }
+                    Err(skipped) => skipped.report(),
+                }
+            }
+        }
+    }
+}
+
+/// Visits entries in the same order as a sorted sequential walk, reading the
+/// subdirectories of each directory ahead of time.
+#[derive(Default)]
+struct SortedVisit {
+    received: HashMap<PathBuf, Listing>,
+    /// Prefetched directories that turned out not to be needed.
+    unwanted: HashSet<PathBuf>,
+}
+
+impl SortedVisit {
+    fn visit_dir(
+        &mut self,
+        walk: &mut ParallelWalk,
+        rx: &mpsc::Receiver<Listing>,
+        dir: &Path,
+        visit: &mut dyn FnMut(&DirEntry, usize) -> bool,
+    ) {
+        let Some(Listing {
+            depth,
+            ancestors,
+            mut entries,
+            ..
+        }) = self.wait_for(walk, rx, dir)
+        else {
+            return;
+        };
+        entries.sort_by(|a, b| match (a, b) {
+            (Ok(a), Ok(b)) => a.file_name().cmp(b.file_name()),
+            (Ok(_), Err(_)) => std::cmp::Ordering::Greater,
+            (Err(_), Ok(_)) => std::cmp::Ordering::Less,
+            (Err(_), Err(_)) => std::cmp::Ordering::Equal,
+        });
+
+        for entry in entries.iter().flatten() {
+            if walk.can_descend(entry, depth) {
+                walk.submit(
+                    entry.path().to_path_buf(),
+                    depth + 1,
+                    Arc::clone(&ancestors),
+                );
+            }
+        }
+
+        for entry in entries {
+            match entry {
+                Ok(entry) => {
+                    let descend = visit(&entry, depth);
+                    if walk.can_descend(&entry, depth) {
+                        if descend {
+                            self.visit_dir(walk, rx, entry.path(), visit);
+                        } else {
+                            self.discard(entry.into_path());
+                        }
+                    }
+                }
+                Err(skipped) => skipped.report(),
+            }
+        }
+    }
+
+    /// Receives listings until the one for `dir` arrives.
+    fn wait_for(
+        &mut self,
+        walk: &mut ParallelWalk,
+        rx: &mpsc::Receiver<Listing>,
+        dir: &Path,
+    ) -> Option<Listing> {
+        loop {
+            if let Some(listing) = self.received.remove(dir) {
+                return Some(listing);
+            }
+
+            let listing = walk.receive(rx)?;
+            if self.unwanted.remove(&listing.dir) {
+                continue;
+            }
+            if listing.dir == dir {
+                return Some(listing);
+            }
+            self.received.insert(listing.dir.clone(), listing);
+        }
+    }
+
+    fn discard(&mut self, dir: PathBuf) {
+        if self.received.remove(&dir).is_none() {
+            self.unwanted.insert(dir);
+        }
+    }
+}
+
+fn read_dirs(
+    worker: &Worker<Job>,
+    injector: &Injector<Job>,
+    stealers: &[Stealer<Job>],
+    done: &AtomicBool,
+    options: &WalkOptions,
+    root_device: Option<u64>,
+    tx: mpsc::Sender<Listing>,
+) {
+    let follow_links = options.follow_links;
+    loop {
+        let Some(job) = find_job(worker, injector, stealers) else {
+            if done.load(Ordering::Acquire) {
+                return;
+            }
+            thread::park_timeout(Duration::from_millis(10));
+            continue;
+        };
+
+        let mut ancestors = job.ancestors;
+        if follow_links {
+            if let Ok(canonical) = fs::canonicalize(&job.dir) {
+                Arc::make_mut(&mut ancestors).push((job.dir.clone(), canonical));
+            }
+        }
+
+        // Leave a directory on another file system empty, as if it were
+        // never descended into.
+        let entries = if root_device.is_some() && device(&job.dir).ok() != root_device {
+            vec![]
+        } else {
+            WalkDir::new(&job.dir)
+                .min_depth(1)
+                .max_depth(1)
+                .follow_links(follow_links)
+                .into_iter()
+                .map(|entry| match entry {
+                    Ok(entry) if follow_links => check_loop(entry, &ancestors),
+                    Ok(entry) => Ok(entry),
+                    Err(e) => broken_link(&e).ok_or(Skipped::Error(e)),
+                })
+                .collect()
+        };
+        let listing = Listing {
+            dir: job.dir,
+            depth: job.depth,
+            ancestors,
+            entries,
+        };
+        if tx.send(listing).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(unix)]
+fn device(path: &Path) -> io::Result<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    fs::metadata(path).map(|metadata| metadata.dev())
+}
+
+/// Other platforms walk sequentially to stay on one file system.
+#[cfg(not(unix))]
+fn device(_path: &Path) -> io::Result<u64> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Skips a directory that is also one of `ancestors`, the directories above
+/// it, which can only happen through links.
+fn check_loop(entry: DirEntry, ancestors: &[(PathBuf, PathBuf)]) -> Result<DirEntry, Skipped> {
+    if !entry.file_type().is_dir() {
+        return Ok(entry);
+    }
+    let Ok(canonical) = fs::canonicalize(entry.path()) else {
+        return Ok(entry);
+    };
+
+    match ancestors.iter().find(|(_, dir)| *dir == canonical) {
+        Some((ancestor, _)) => Err(Skipped::Loop {
+            path: entry.into_path(),
+            ancestor: ancestor.clone(),
+        }),
+        None => Ok(entry),
+    }
+}
+
+/// Takes a job from this thread's queue, then the shared queue, then from
+/// the other threads.
+fn find_job(
+    worker: &Worker<Job>,
+    injector: &Injector<Job>,
+    stealers: &[Stealer<Job>],
+) -> Option<Job> {
+    worker.pop().or_else(|| {
+        iter::repeat_with(|| {
+            injector
+                .steal_batch_and_pop(worker)
+                .or_else(|| stealers.iter().map(Stealer::steal).collect())
+        })
+        .find(|steal| !steal.is_retry())
+        .and_then(|steal| steal.success())
+    })
+}
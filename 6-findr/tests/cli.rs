@@ -3,7 +3,12 @@ use assert_cmd::Command;
 use predicates::prelude::*;
 use pretty_assertions::assert_eq;
 use rand::{distributions::Alphanumeric, Rng};
-use std::{borrow::Cow, fs, path::Path};
+use std::{
+    borrow::Cow,
+    fs,
+    path::Path,
+    time::{Duration, SystemTime},
+};
 
 const PRG: &str = "findr";
 
@@ -39,10 +44,10 @@ fn skips_bad_dir() -> Result<()> {
 #[test]
 fn dies_bad_name() -> Result<()> {
     Command::cargo_bin(PRG)?
-        .args(["--name", "*.csv"])
+        .args(["--name", "[.csv"])
         .assert()
         .failure()
-        .stderr(predicate::str::contains("error: invalid value '*.csv'"));
+        .stderr(predicate::str::contains("error: invalid value '[.csv'"));
     Ok(())
 }
 
@@ -60,14 +65,14 @@ fn dies_bad_type() -> Result<()> {
 
 // --------------------------------------------------
 #[cfg(windows)]
-fn format_file_name(expected_file: &str) -> Cow<str> {
+fn format_file_name(expected_file: &str) -> Cow<'_, str> {
     // Equivalent to: Cow::Owned(format!("{}.windows", expected_file))
     format!("{}.windows", expected_file).into()
 }
 
 // --------------------------------------------------
 #[cfg(not(windows))]
-fn format_file_name(expected_file: &str) -> Cow<str> {
+fn format_file_name(expected_file: &str) -> Cow<'_, str> {
     // Equivalent to: Cow::Borrowed(expected_file)
     expected_file.into()
 }
@@ -76,15 +81,13 @@ fn format_file_name(expected_file: &str) -> Cow<str> {
 fn run(args: &[&str], expected_file: &str) -> Result<()> {
     let file = format_file_name(expected_file);
     let contents = fs::read_to_string(file.as_ref())?;
-    let mut expected: Vec<&str> =
-        contents.split('\n').filter(|s| !s.is_empty()).collect();
+    let mut expected: Vec<&str> = contents.split('\n').filter(|s| !s.is_empty()).collect();
     expected.sort();
 
     let cmd = Command::cargo_bin(PRG)?.args(args).assert().success();
     let out = cmd.get_output();
     let stdout = String::from_utf8(out.stdout.clone())?;
-    let mut lines: Vec<&str> =
-        stdout.split('\n').filter(|s| !s.is_empty()).collect();
+    let mut lines: Vec<&str> = stdout.split('\n').filter(|s| !s.is_empty()).collect();
     lines.sort();
 
     assert_eq!(lines, expected);
@@ -228,7 +231,7 @@ fn type_f_l() -> Result<()> {
 #[test]
 fn name_csv() -> Result<()> {
     run(
-        &["tests/inputs", "-n", ".*[.]csv"],
+        &["tests/inputs", "-n", "*.csv"],
         "tests/expected/name_csv.txt",
     )
 }
@@ -237,7 +240,7 @@ fn name_csv() -> Result<()> {
 #[test]
 fn name_csv_mp3() -> Result<()> {
     run(
-        &["tests/inputs", "-n", ".*[.]csv", "-n", ".*[.]mp3"],
+        &["tests/inputs", "-n", "*.csv", "-n", "*.mp3"],
         "tests/expected/name_csv_mp3.txt",
     )
 }
@@ -246,7 +249,7 @@ fn name_csv_mp3() -> Result<()> {
 #[test]
 fn name_txt_path_a_d() -> Result<()> {
     run(
-        &["tests/inputs/a", "tests/inputs/d", "--name", ".*.txt"],
+        &["tests/inputs/a", "tests/inputs/d", "--name", "*.txt"],
         "tests/expected/name_txt_path_a_d.txt",
     )
 }
@@ -254,14 +257,14 @@ fn name_txt_path_a_d() -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn name_a() -> Result<()> {
-    run(&["tests/inputs", "-n", "a"], "tests/expected/name_a.txt")
+    run(&["tests/inputs", "-n", "a*"], "tests/expected/name_a.txt")
 }
 
 // --------------------------------------------------
 #[test]
 fn type_f_name_a() -> Result<()> {
     run(
-        &["tests/inputs", "-t", "f", "-n", "a"],
+        &["tests/inputs", "-t", "f", "-n", "a*"],
         "tests/expected/type_f_name_a.txt",
     )
 }
@@ -270,7 +273,7 @@ fn type_f_name_a() -> Result<()> {
 #[test]
 fn type_d_name_a() -> Result<()> {
     run(
-        &["tests/inputs", "--type", "d", "--name", "a"],
+        &["tests/inputs", "--type", "d", "--name", "a*"],
         "tests/expected/type_d_name_a.txt",
     )
 }
@@ -303,8 +306,7 @@ fn unreadable_dir() -> Result<()> {
 
     let out = cmd.get_output();
     let stdout = String::from_utf8(out.stdout.clone())?;
-    let lines: Vec<&str> =
-        stdout.split('\n').filter(|s| !s.is_empty()).collect();
+    let lines: Vec<&str> = stdout.split('\n').filter(|s| !s.is_empty()).collect();
 
     assert_eq!(lines.len(), 17);
 
@@ -312,3 +314,1406 @@ fn unreadable_dir() -> Result<()> {
     assert!(stderr.contains("cant-touch-this: Permission denied"));
     Ok(())
 }
+
+// --------------------------------------------------
+/// Runs findr, in `dir` if given and with `env` set, checks that it
+/// succeeds and returns its output.
+fn stdout_of(dir: Option<&Path>, env: &[(&str, &Path)], args: &[&str]) -> Result<String> {
+    let mut cmd = Command::cargo_bin(PRG)?;
+    if let Some(dir) = dir {
+        cmd.current_dir(dir);
+    }
+    let output = cmd.envs(env.iter().copied()).args(args).output()?;
+    assert!(output.status.success(), "{args:?}: {output:?}");
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+// --------------------------------------------------
+/// Checks the lines findr prints when run in `dir`, in any order.
+fn run_in(dir: &Path, args: &[&str], expected: &[&str]) -> Result<()> {
+    run_in_env(dir, &[], args, expected)
+}
+
+// --------------------------------------------------
+fn run_in_env(dir: &Path, env: &[(&str, &Path)], args: &[&str], expected: &[&str]) -> Result<()> {
+    let stdout = stdout_of(Some(dir), env, args)?;
+    let mut lines: Vec<&str> = stdout.split('\n').filter(|s| !s.is_empty()).collect();
+    lines.sort();
+
+    let mut expected = expected.to_vec();
+    expected.sort();
+    assert_eq!(lines, expected);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+fn set_age(path: &Path, secs: u64) -> Result<()> {
+    let time = SystemTime::now() - Duration::from_secs(secs);
+    fs::File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(time)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn size_c() -> Result<()> {
+    run(
+        &["tests/inputs", "--size", "2c"],
+        "tests/expected/type_f.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn size_units() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join("empty"), "")?;
+    fs::write(dir.path().join("small"), vec![0; 100])?;
+    fs::write(dir.path().join("big"), vec![0; 3000])?;
+
+    run_in(dir.path(), &["-t", "f", "--size", "-1k"], &["./empty"])?;
+    run_in(dir.path(), &["-t", "f", "--size", "+2k"], &["./big"])?;
+    run_in(dir.path(), &["-t", "f", "--size", "1"], &["./small"])?;
+    run_in(
+        dir.path(),
+        &["-t", "f", "--size", "+0", "--size", "-4k"],
+        &["./small", "./big"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_size() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--size", "+10x"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid size unit"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn mtime_mmin() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join("new"), "")?;
+    fs::write(dir.path().join("hour"), "")?;
+    fs::write(dir.path().join("old"), "")?;
+    set_age(&dir.path().join("hour"), 60 * 60)?;
+    set_age(&dir.path().join("old"), 10 * 24 * 60 * 60)?;
+
+    run_in(dir.path(), &["-t", "f", "--mtime", "+7"], &["./old"])?;
+    run_in(
+        dir.path(),
+        &["-t", "f", "--mtime", "-7"],
+        &["./new", "./hour"],
+    )?;
+    run_in(
+        dir.path(),
+        &["-t", "f", "--mtime", "0"],
+        &["./new", "./hour"],
+    )?;
+    run_in(dir.path(), &["-t", "f", "--mmin", "-30"], &["./new"])?;
+    run_in(
+        dir.path(),
+        &["-t", "f", "--mmin", "+30"],
+        &["./hour", "./old"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn newer() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join("new"), "")?;
+    fs::write(dir.path().join("reference"), "")?;
+    fs::write(dir.path().join("old"), "")?;
+    set_age(&dir.path().join("reference"), 60)?;
+    set_age(&dir.path().join("old"), 120)?;
+
+    run_in(dir.path(), &["-t", "f", "--newer", "reference"], &["./new"])
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_newer() -> Result<()> {
+    let bad = gen_bad_file();
+    let expected = format!("{}: .* [(]os error 2[)]", &bad);
+    Command::cargo_bin(PRG)?
+        .args(["--newer", &bad])
+        .assert()
+        .failure()
+        .stderr(predicate::str::is_match(expected)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn empty() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir_all(dir.path().join("full/empty_dir"))?;
+    fs::write(dir.path().join("full/file"), "x")?;
+    fs::write(dir.path().join("empty_file"), "")?;
+
+    run_in(
+        dir.path(),
+        &["--empty"],
+        &["./empty_file", "./full/empty_dir"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn name_glob() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir(dir.path().join("src"))?;
+    for file in ["src/main.rs", "src/Lib.RS", "parsers.json", "Cargo.toml"] {
+        fs::write(dir.path().join(file), "")?;
+    }
+
+    run_in(dir.path(), &["-name", "*.rs"], &["./src/main.rs"])?;
+    run_in(dir.path(), &["-n", "*.rs"], &["./src/main.rs"])?;
+    run_in(
+        dir.path(),
+        &["-iname", "*.rs"],
+        &["./src/Lib.RS", "./src/main.rs"],
+    )?;
+    run_in(
+        dir.path(),
+        &["-name", "*.{json,toml}"],
+        &["./Cargo.toml", "./parsers.json"],
+    )?;
+    run_in(dir.path(), &["-name", "[cp]*"], &["./parsers.json"])?;
+    run_in(
+        dir.path(),
+        &["-name", "?a*", "-type", "f"],
+        &["./Cargo.toml", "./parsers.json", "./src/main.rs"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn path_glob() -> Result<()> {
+    run_in(
+        Path::new("tests/inputs"),
+        &["-path", "./a/*.csv"],
+        &["./a/b/b.csv"],
+    )?;
+    run_in(
+        Path::new("tests/inputs"),
+        &["-ipath", "./D/*.T[SX]?"],
+        &["./d/d.tsv", "./d/d.txt"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn regex() -> Result<()> {
+    run_in(
+        Path::new("tests/inputs"),
+        &["-regex", ".*/[bc]/.*mp3"],
+        &["./a/b/c/c.mp3"],
+    )?;
+    run_in(
+        Path::new("tests/inputs"),
+        &["-iregex", "^./D/.*[.]CSV$"],
+        &["./d/b.csv"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn regex_whole_path() -> Result<()> {
+    run_in(Path::new("tests/inputs"), &["-regex", "csv"], &[])?;
+    run_in(Path::new("tests/inputs"), &["-regex", "./d/b"], &[])?;
+    run_in(
+        Path::new("tests/inputs"),
+        &["-regex", r".*\.csv"],
+        &["./a/b/b.csv", "./d/b.csv", "./g.csv"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_regex() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-regex", "("])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value '(' for '-regex'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn expr_or_not() -> Result<()> {
+    run(
+        &[
+            "tests/inputs",
+            "(",
+            "-name",
+            "*csv",
+            "-o",
+            "-name",
+            "*mp3",
+            ")",
+            "-not",
+            "-type",
+            "l",
+        ],
+        "tests/expected/expr_or_not.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn expr_not_name() -> Result<()> {
+    run(
+        &["tests/inputs", "-type", "f", "!", "-name", "*.txt"],
+        "tests/expected/expr_not_name.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn expr_and_or() -> Result<()> {
+    run(
+        &[
+            "tests/inputs",
+            "-name",
+            "[ab]*",
+            "-type",
+            "d",
+            "-o",
+            "-name",
+            "*tsv",
+        ],
+        "tests/expected/expr_and_or.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn expr_with_options() -> Result<()> {
+    run(
+        &["tests/inputs", "-name", "*.csv", "-t", "f", "-o", "-true"],
+        "tests/expected/type_f.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_expression() -> Result<()> {
+    for (args, expected) in [
+        (vec!["(", "-type", "f"], "missing ')' in expression"),
+        (vec!["-type", "f", ")"], "unexpected ')' in expression"),
+        (vec!["-type", "f", "-o"], "expected an expression"),
+        (vec!["-type"], "missing argument to '-type'"),
+        (vec!["-type", "x"], "invalid value 'x' for '-type'"),
+    ] {
+        Command::cargo_bin(PRG)?
+            .args(args)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(expected));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn max_depth_1() -> Result<()> {
+    run(
+        &["tests/inputs", "--max-depth", "1"],
+        "tests/expected/max_depth_1.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn min_depth_2() -> Result<()> {
+    run(
+        &["tests/inputs", "--min-depth", "2", "-t", "f"],
+        "tests/expected/min_depth_2.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn depth_2() -> Result<()> {
+    run(
+        &["tests/inputs", "--min-depth", "2", "--max-depth", "2"],
+        "tests/expected/depth_2.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn prune() -> Result<()> {
+    run(
+        &[
+            "tests/inputs",
+            "-name",
+            "[ad]",
+            "-prune",
+            "-o",
+            "-type",
+            "f",
+        ],
+        "tests/expected/prune_a_d.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn exec() -> Result<()> {
+    run(
+        &["tests/inputs", "-type", "f", "-exec", "echo", "{}", ";"],
+        "tests/expected/type_f.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn exec_status() -> Result<()> {
+    run(
+        &[
+            "tests/inputs",
+            "-exec",
+            "test",
+            "{}",
+            "=",
+            "tests/inputs/g.csv",
+            ";",
+            "-print",
+        ],
+        "tests/expected/path_g.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn exec_batch() -> Result<()> {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "-type", "f", "-exec", "echo", "{}", "+"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert_eq!(stdout.lines().count(), 1);
+
+    let mut paths: Vec<&str> = stdout.split_whitespace().collect();
+    paths.sort();
+    let contents = fs::read_to_string("tests/expected/type_f.txt")?;
+    let mut expected: Vec<&str> = contents.lines().collect();
+    expected.sort();
+    assert_eq!(paths, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn dies_exec_batch_failure() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "-exec", "false", "{}", "+"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("false returned a non-zero status"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn execdir() -> Result<()> {
+    let cmd = Command::cargo_bin(PRG)?
+        .args([
+            "tests/inputs/a",
+            "-type",
+            "f",
+            "-execdir",
+            "echo",
+            "{}",
+            ";",
+        ])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let mut lines: Vec<&str> = stdout.lines().collect();
+    lines.sort();
+    assert_eq!(lines, ["./a.txt", "./b.csv", "./c.mp3"]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn ok() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/g.csv", "-ok", "echo", "yes", "{}", ";"])
+        .write_stdin("y\n")
+        .assert()
+        .success()
+        .stdout("yes tests/inputs/g.csv\n")
+        .stderr("< echo yes tests/inputs/g.csv > ? ");
+
+    Command::cargo_bin(PRG)?
+        .args([
+            "tests/inputs/g.csv",
+            "-ok",
+            "echo",
+            "{}",
+            ";",
+            "-o",
+            "-print",
+        ])
+        .write_stdin("n\n")
+        .assert()
+        .success()
+        .stdout("tests/inputs/g.csv\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn print0() -> Result<()> {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "-type", "f", "-print0"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert!(stdout.ends_with('\0'));
+    assert!(!stdout.contains('\n'));
+
+    let mut paths: Vec<&str> = stdout.split('\0').filter(|s| !s.is_empty()).collect();
+    paths.sort();
+    let contents = fs::read_to_string(format_file_name("tests/expected/type_f.txt").as_ref())?;
+    let mut expected: Vec<&str> = contents.lines().collect();
+    expected.sort();
+    assert_eq!(paths, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn printf() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([
+            "tests/inputs/a/b/c",
+            "-type",
+            "f",
+            "-printf",
+            r"%p %f %h %s %y %d %%\t\\\n",
+        ])
+        .assert()
+        .success()
+        .stdout("tests/inputs/a/b/c/c.mp3 c.mp3 tests/inputs/a/b/c 2 f 1 %\t\\\n");
+
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/d", "-name", "b*", "-printf", "%y\n"])
+        .assert()
+        .success()
+        .stdout("l\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_printf() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-printf", "%z"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown format directive '%z'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn delete() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir_all(dir.path().join("a/b/c"))?;
+    fs::write(dir.path().join("a/b/c/file"), "")?;
+    fs::write(dir.path().join("a/keep"), "")?;
+
+    Command::cargo_bin(PRG)?
+        .current_dir(dir.path())
+        .args(["a", "-name", "{b,c,file}", "-delete"])
+        .assert()
+        .success()
+        .stdout("");
+
+    run_in(dir.path(), &["."], &[".", "./a", "./a/keep"])
+}
+
+// --------------------------------------------------
+#[test]
+fn delete_dot() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join("file"), "")?;
+
+    Command::cargo_bin(PRG)?
+        .current_dir(dir.path())
+        .arg("-delete")
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+    run_in(dir.path(), &["."], &["."])
+}
+
+// --------------------------------------------------
+#[test]
+fn delete_failure_status() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir_all(dir.path().join("nonempty"))?;
+    fs::write(dir.path().join("nonempty/file"), "")?;
+    fs::create_dir_all(dir.path().join("empty"))?;
+
+    Command::cargo_bin(PRG)?
+        .current_dir(dir.path())
+        .args([".", "-type", "d", "-name", "*empty", "-delete"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("./nonempty: cannot delete: "))
+        .stderr(predicate::str::ends_with(
+            "-delete: some entries were not deleted\n",
+        ));
+    run_in(dir.path(), &["."], &[".", "./nonempty", "./nonempty/file"])
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn print_non_utf8() -> Result<()> {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = tempfile::tempdir()?;
+    let name = OsStr::from_bytes(b"caf\xe9");
+    fs::write(dir.path().join(name), "")?;
+
+    Command::cargo_bin(PRG)?
+        .current_dir(dir.path())
+        .args([".", "-type", "f"])
+        .assert()
+        .success()
+        .stdout(&b"./caf\xe9\n"[..]);
+    Ok(())
+}
+
+// --------------------------------------------------
+fn gitignore_fixture() -> Result<tempfile::TempDir> {
+    let dir = tempfile::tempdir()?;
+    let root = dir.path();
+    for subdir in [
+        "home/.config/git",
+        "repo/.git/info",
+        "repo/build",
+        "repo/logs",
+        "repo/sub/build",
+    ] {
+        fs::create_dir_all(root.join(subdir))?;
+    }
+    fs::write(root.join("home/.config/git/ignore"), "*.bak\n")?;
+    fs::write(root.join("repo/.git/info/exclude"), "secret*\n")?;
+    fs::write(
+        root.join("repo/.gitignore"),
+        "build/\n*.log\n!keep.log\n/top.txt\n",
+    )?;
+    fs::write(root.join("repo/sub/.ignore"), "*.tmp\n")?;
+    for file in [
+        "repo/.hidden",
+        "repo/a.txt",
+        "repo/top.txt",
+        "repo/x.log",
+        "repo/keep.log",
+        "repo/logs/y.log",
+        "repo/secret.txt",
+        "repo/f.bak",
+        "repo/build/o",
+        "repo/sub/top.txt",
+        "repo/sub/z.tmp",
+        "repo/sub/build/o",
+    ] {
+        fs::write(root.join(file), "")?;
+    }
+    Ok(dir)
+}
+
+// --------------------------------------------------
+/// Runs findr in `dir` with the global excludes of gitignore_fixture().
+fn run_gitignore(dir: &Path, args: &[&str], expected: &[&str]) -> Result<()> {
+    let home = dir.join("../home");
+    let config = home.join(".config");
+    let env = [
+        ("HOME", home.as_path()),
+        ("XDG_CONFIG_HOME", config.as_path()),
+    ];
+    run_in_env(dir, &env, args, expected)
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn gitignore() -> Result<()> {
+    let dir = gitignore_fixture()?;
+    let repo = dir.path().join("repo");
+
+    run_gitignore(
+        &repo,
+        &["--gitignore"],
+        &[
+            ".",
+            "./a.txt",
+            "./keep.log",
+            "./logs",
+            "./sub",
+            "./sub/top.txt",
+        ],
+    )?;
+    run_gitignore(
+        &repo,
+        &["--gitignore", "--hidden", "--max-depth", "1", "-type", "f"],
+        &["./.gitignore", "./.hidden", "./a.txt", "./keep.log"],
+    )?;
+    // Even with --hidden, .git itself is skipped.
+    run_gitignore(
+        &repo,
+        &["--gitignore", "--hidden", "-path", "*git*"],
+        &["./.gitignore"],
+    )?;
+
+    // Ignore files above the start path still apply.
+    run_gitignore(&repo.join("sub"), &["--gitignore"], &[".", "./top.txt"])
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn gitignore_delete() -> Result<()> {
+    let dir = gitignore_fixture()?;
+    let repo = dir.path().join("repo");
+
+    run_gitignore(
+        &repo,
+        &["--gitignore", "-name", "{o,*top*}", "-delete"],
+        &[],
+    )?;
+    assert!(repo.join("build/o").exists());
+    assert!(repo.join("sub/build/o").exists());
+    assert!(repo.join("top.txt").exists());
+    assert!(!repo.join("sub/top.txt").exists());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_hidden_without_gitignore() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .arg("--hidden")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--gitignore"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn threads_sort() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    for i in 0..8 {
+        for j in 0..8 {
+            let sub = dir.path().join(format!("d{i}/e{j}"));
+            fs::create_dir_all(&sub)?;
+            fs::write(sub.join("f.txt"), "")?;
+        }
+    }
+    let root = dir.path().to_str().unwrap();
+
+    let sequential = stdout_of(None, &[], &[root, "--sort"])?;
+    for threads in ["2", "4", "16"] {
+        let parallel = stdout_of(None, &[], &[root, "--sort", "--threads", threads])?;
+        assert_eq!(parallel, sequential);
+    }
+
+    // Without --sort, the same entries come out in any order.
+    let mut expected: Vec<_> = sequential.lines().collect();
+    expected.sort();
+    let parallel = stdout_of(None, &[], &[root, "--threads", "4"])?;
+    let mut lines: Vec<_> = parallel.lines().collect();
+    lines.sort();
+    assert_eq!(lines, expected);
+
+    let sequential = stdout_of(
+        None,
+        &[],
+        &[root, "--sort", "-name", "e[0-3]", "-prune", "-o", "-print"],
+    )?;
+    let parallel = stdout_of(
+        None,
+        &[],
+        &[
+            root,
+            "--sort",
+            "--threads",
+            "4",
+            "-name",
+            "e[0-3]",
+            "-prune",
+            "-o",
+            "-print",
+        ],
+    )?;
+    assert_eq!(parallel, sequential);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn threads_depth() -> Result<()> {
+    run_in(
+        Path::new("tests/inputs"),
+        &[
+            "--threads",
+            "4",
+            "--min-depth",
+            "1",
+            "--max-depth",
+            "1",
+            "-type",
+            "d",
+        ],
+        &["./a", "./d", "./f"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_zero_threads() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--threads", "0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--threads"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[cfg(not(windows))]
+fn perm_fixture() -> Result<tempfile::TempDir> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir()?;
+    for (file, mode) in [("open", 0o666), ("private", 0o600), ("script", 0o755)] {
+        let path = dir.path().join(file);
+        fs::write(&path, "")?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode))?;
+    }
+    fs::hard_link(dir.path().join("script"), dir.path().join("link"))?;
+    Ok(dir)
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn perm() -> Result<()> {
+    let dir = perm_fixture()?;
+    let dir = dir.path();
+
+    run_in(dir, &["-perm", "-o+w"], &["./open"])?;
+    run_in(dir, &["-perm", "-002", "-type", "f"], &["./open"])?;
+    run_in(dir, &["-perm", "600"], &["./private"])?;
+    run_in(dir, &["-perm", "u=rw,go=rw"], &["./open"])?;
+    run_in(
+        dir,
+        &["-type", "f", "-perm", "/111"],
+        &["./link", "./script"],
+    )?;
+    run_in(
+        dir,
+        &["-type", "f", "!", "-perm", "/go=rwx"],
+        &["./private"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn links() -> Result<()> {
+    let dir = perm_fixture()?;
+
+    run_in(
+        dir.path(),
+        &["-type", "f", "-links", "+1"],
+        &["./link", "./script"],
+    )?;
+    run_in(
+        dir.path(),
+        &["-type", "f", "-links", "1"],
+        &["./open", "./private"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn user_group() -> Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    let dir = perm_fixture()?;
+    let metadata = fs::metadata(dir.path().join("open"))?;
+    let (uid, gid) = (metadata.uid().to_string(), metadata.gid().to_string());
+    let all = [".", "./link", "./open", "./private", "./script"];
+
+    run_in(dir.path(), &["-user", &uid], &all)?;
+    run_in(dir.path(), &["-group", &gid], &all)?;
+    run_in(dir.path(), &["-nouser", "-o", "-nogroup"], &[])?;
+
+    // Only root can give files away to ids without a user or group.
+    let orphan = dir.path().join("orphan");
+    fs::write(&orphan, "")?;
+    if std::os::unix::fs::chown(&orphan, Some(65432), Some(65432)).is_ok() {
+        run_in(dir.path(), &["-nouser"], &["./orphan"])?;
+        run_in(dir.path(), &["-nogroup"], &["./orphan"])?;
+        run_in(dir.path(), &["-user", "65432"], &["./orphan"])?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn dies_bad_user() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-user", "no-such-user-findr"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no such user"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[cfg(not(windows))]
+fn link_fixture() -> Result<tempfile::TempDir> {
+    use std::os::unix::fs::symlink;
+
+    let dir = tempfile::tempdir()?;
+    fs::create_dir(dir.path().join("real"))?;
+    fs::write(dir.path().join("real/file"), "")?;
+    symlink("..", dir.path().join("real/up"))?;
+    symlink("real", dir.path().join("link"))?;
+    symlink("nowhere", dir.path().join("broken"))?;
+    Ok(dir)
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn follow_links() -> Result<()> {
+    let dir = link_fixture()?;
+    let dir = dir.path();
+
+    let never = [
+        ".",
+        "./broken",
+        "./link",
+        "./real",
+        "./real/file",
+        "./real/up",
+    ];
+    run_in(dir, &[], &never)?;
+    run_in(dir, &["-P"], &never)?;
+    run_in(dir, &["-L", "-P"], &never)?;
+
+    let always = [
+        ".",
+        "./broken",
+        "./link",
+        "./link/file",
+        "./real",
+        "./real/file",
+    ];
+    run_in(dir, &["-L"], &always)?;
+    run_in(dir, &["-H", "-L", "--threads", "4"], &always)?;
+
+    run_in(dir, &["link"], &["link"])?;
+    run_in(dir, &["-H", "link"], &["link", "link/file", "link/up"])?;
+    run_in(
+        dir,
+        &["-H", "link", "--threads", "4"],
+        &["link", "link/file", "link/up"],
+    )?;
+
+    // Broken links are still links when following them.
+    run_in(dir, &["-L", "-type", "l"], &["./broken"])
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn follow_links_loop() -> Result<()> {
+    let dir = link_fixture()?;
+
+    for threads in ["1", "4"] {
+        Command::cargo_bin(PRG)?
+            .current_dir(dir.path())
+            .args(["-L", "--sort", "--threads", threads])
+            .assert()
+            .success()
+            .stdout(".\n./broken\n./link\n./link/file\n./real\n./real/file\n")
+            .stderr(predicate::str::contains(
+                "warning: File system loop found: ./real/up points to an ancestor .",
+            ));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn xtype() -> Result<()> {
+    let dir = link_fixture()?;
+    let dir = dir.path();
+
+    run_in(dir, &["-xtype", "l"], &["./broken"])?;
+    run_in(
+        dir,
+        &["-xtype", "d"],
+        &[".", "./link", "./real", "./real/up"],
+    )?;
+    run_in(dir, &["-L", "-xtype", "l"], &["./broken", "./link"])?;
+    run_in(dir, &["-L", "-xtype", "f"], &["./link/file", "./real/file"])
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn delete_outside_start() -> Result<()> {
+    use std::os::unix::fs::symlink;
+
+    let dir = tempfile::tempdir()?;
+    fs::create_dir_all(dir.path().join("outside"))?;
+    fs::write(dir.path().join("outside/file"), "")?;
+    fs::create_dir_all(dir.path().join("start"))?;
+    symlink("../outside", dir.path().join("start/link"))?;
+
+    Command::cargo_bin(PRG)?
+        .current_dir(dir.path())
+        .args(["-L", "start", "-name", "file", "-delete"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "start/link/file: cannot delete: outside of the start path",
+        ));
+    assert!(dir.path().join("outside/file").exists());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn xdev() -> Result<()> {
+    if !Path::new("/proc/self").exists() {
+        return Ok(());
+    }
+
+    for threads in ["1", "4"] {
+        let args = ["/", "--max-depth", "2", "--threads", threads];
+        let output = Command::cargo_bin(PRG)?
+            .args(args)
+            .args(["-path", "/proc/*"])
+            .output()?;
+        assert!(!output.stdout.is_empty());
+
+        // The mount point is still listed, but not what's below it.
+        Command::cargo_bin(PRG)?
+            .args(args)
+            .args(["-xdev", "-path", "/proc*"])
+            .assert()
+            .success()
+            .stdout("/proc\n");
+        Command::cargo_bin(PRG)?
+            .args(args)
+            .args(["--one-file-system", "-path", "/proc*"])
+            .assert()
+            .success()
+            .stdout("/proc\n");
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn fstype() -> Result<()> {
+    if !Path::new("/proc/self").exists() {
+        return Ok(());
+    }
+
+    Command::cargo_bin(PRG)?
+        .args(["/proc", "/", "--max-depth", "0", "-fstype", "proc"])
+        .assert()
+        .success()
+        .stdout("/proc\n");
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "-fstype", "no-such-type"])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+fn duplicates_fixture() -> Result<tempfile::TempDir> {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir(dir.path().join("sub"))?;
+    let big = "x".repeat(10_000);
+    for (file, contents) in [
+        ("a", "abc"),
+        ("sub/b", "abc"),
+        ("c", "abd"),
+        ("big1", big.as_str()),
+        ("sub/big2", big.as_str()),
+        ("empty1", ""),
+        ("empty2", ""),
+    ] {
+        fs::write(dir.path().join(file), contents)?;
+    }
+    Ok(dir)
+}
+
+// --------------------------------------------------
+#[test]
+fn duplicates() -> Result<()> {
+    let dir = duplicates_fixture()?;
+
+    for threads in ["1", "4"] {
+        Command::cargo_bin(PRG)?
+            .current_dir(dir.path())
+            .args(["--duplicates", "--threads", threads])
+            .assert()
+            .success()
+            .stdout("./big1\n./sub/big2\n\n./a\n./sub/b\n");
+    }
+
+    Command::cargo_bin(PRG)?
+        .current_dir(dir.path())
+        .args(["--duplicates", "--min-size", "1k"])
+        .assert()
+        .success()
+        .stdout("./big1\n./sub/big2\n");
+    Command::cargo_bin(PRG)?
+        .current_dir(dir.path())
+        .args(["--duplicates", "--min-size", "0", "-name", "[ae]*"])
+        .assert()
+        .success()
+        .stdout("./empty1\n./empty2\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn duplicates_hard_links() -> Result<()> {
+    let dir = duplicates_fixture()?;
+    fs::hard_link(dir.path().join("c"), dir.path().join("0c"))?;
+
+    Command::cargo_bin(PRG)?
+        .current_dir(dir.path())
+        .args(["--duplicates", "--max-depth", "1"])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn duplicates_json() -> Result<()> {
+    let dir = duplicates_fixture()?;
+
+    let output = Command::cargo_bin(PRG)?
+        .current_dir(dir.path())
+        .args(["--duplicates", "--output", "json"])
+        .output()?;
+    assert!(output.status.success());
+    let groups: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(
+        groups,
+        serde_json::json!([
+            { "size": 10_000, "paths": ["./big1", "./sub/big2"] },
+            { "size": 3, "paths": ["./a", "./sub/b"] },
+        ])
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_duplicates_with_action() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--duplicates", "-print"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--duplicates can't be combined with actions",
+        ));
+    Command::cargo_bin(PRG)?
+        .args(["--min-size", "1"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--duplicates"));
+    Ok(())
+}
+
+// --------------------------------------------------
+fn sort_fixture() -> Result<tempfile::TempDir> {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir(dir.path().join("sub"))?;
+    for (name, contents, age) in [
+        ("big", "1234567890", 300),
+        ("small", "1", 100),
+        ("sub/mid", "12345", 200),
+    ] {
+        let path = dir.path().join(name);
+        fs::write(&path, contents)?;
+        set_age(&path, age)?;
+    }
+    Ok(dir)
+}
+
+// --------------------------------------------------
+#[test]
+fn sort_keys() -> Result<()> {
+    let dir = sort_fixture()?;
+    let dir = dir.path();
+
+    for (args, expected) in [
+        (vec!["--sort"], "./big\n./small\n./sub/mid\n"),
+        (vec!["--sort", "name"], "./big\n./small\n./sub/mid\n"),
+        (vec!["--sort", "size"], "./small\n./sub/mid\n./big\n"),
+        (vec!["--sort", "mtime"], "./big\n./sub/mid\n./small\n"),
+        (vec!["--sort", "depth"], "./big\n./small\n./sub/mid\n"),
+        (
+            vec!["--sort", "size", "--reverse"],
+            "./big\n./sub/mid\n./small\n",
+        ),
+        (vec!["--sort", "--reverse"], "./sub/mid\n./small\n./big\n"),
+    ] {
+        let mut args = args;
+        args.extend(["-type", "f"]);
+        assert_eq!(stdout_of(Some(dir), &[], &args)?, expected, "{args:?}");
+    }
+
+    // Ties keep name order, also when the key is reversed.
+    assert_eq!(
+        stdout_of(Some(dir), &[], &["--sort", "depth", "-name", "*i*"])?,
+        "./big\n./sub/mid\n"
+    );
+    assert_eq!(
+        stdout_of(
+            Some(dir),
+            &[],
+            &["--sort", "depth", "--reverse", "-name", "*i*"]
+        )?,
+        "./sub/mid\n./big\n"
+    );
+    fs::write(dir.join("small2"), "2")?;
+    assert_eq!(
+        stdout_of(
+            Some(dir),
+            &[],
+            &["--sort", "size", "--reverse", "-type", "f"]
+        )?,
+        "./big\n./sub/mid\n./small\n./small2\n"
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_reverse_without_sort() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .arg("--reverse")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--sort"));
+    Command::cargo_bin(PRG)?
+        .args(["--sort", "color"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value 'color'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_sort_output_with_print0_printf() -> Result<()> {
+    for args in [
+        &["--sort", "size", "-print0"][..],
+        &["--sort", "--reverse", "-printf", "%p %s\n"],
+        &["--output", "csv", "-printf", "%p\n"],
+        &["--output", "json", "-print", "-print0"],
+    ] {
+        Command::cargo_bin(PRG)?
+            .args(args)
+            .assert()
+            .failure()
+            .stdout("")
+            .stderr(predicate::str::contains(
+                "only apply to -print, not -print0 or -printf",
+            ));
+    }
+
+    // Sorting by name is the order of the walk, which any action follows.
+    let dir = sort_fixture()?;
+    assert_eq!(
+        stdout_of(Some(dir.path()), &[], &["--sort", "-type", "f", "-print0"])?,
+        "./big\0./small\0./sub/mid\0"
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn output_without_print() -> Result<()> {
+    let dir = sort_fixture()?;
+    for output in ["json", "csv"] {
+        assert_eq!(
+            stdout_of(
+                Some(dir.path()),
+                &[],
+                &["--output", output, "-exec", "true", ";"]
+            )?,
+            ""
+        );
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn output_json() -> Result<()> {
+    let dir = sort_fixture()?;
+
+    let stdout = stdout_of(
+        Some(dir.path()),
+        &[],
+        &["--output", "json", "--sort", "size", "-type", "f"],
+    )?;
+    let entries: serde_json::Value = serde_json::from_str(&stdout)?;
+    let entries = entries.as_array().unwrap();
+    let paths: Vec<_> = entries.iter().map(|entry| &entry["path"]).collect();
+    assert_eq!(paths, ["./small", "./sub/mid", "./big"]);
+
+    let big = &entries[2];
+    assert_eq!(big["type"], "f");
+    assert_eq!(big["size"], 10);
+    assert!(big["mtime"].as_str().unwrap().contains('T'));
+    #[cfg(unix)]
+    assert!(big["mode"].as_str().unwrap().chars().all(|c| c.is_digit(8)));
+
+    assert_eq!(
+        stdout_of(Some(dir.path()), &[], &["--output", "json", "-false"])?,
+        "[]\n"
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn output_ndjson() -> Result<()> {
+    let dir = sort_fixture()?;
+
+    let stdout = stdout_of(Some(dir.path()), &[], &["--output", "ndjson", "--sort"])?;
+    let entries = stdout
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+    let types: Vec<_> = entries
+        .iter()
+        .map(|entry| format!("{} {}", entry["type"], entry["path"]))
+        .collect();
+    assert_eq!(
+        types,
+        [
+            r#""d" ".""#,
+            r#""f" "./big""#,
+            r#""f" "./small""#,
+            r#""d" "./sub""#,
+            r#""f" "./sub/mid""#,
+        ]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn output_csv() -> Result<()> {
+    let dir = sort_fixture()?;
+    fs::write(dir.path().join("a,\"b\""), "")?;
+
+    let stdout = stdout_of(
+        Some(dir.path()),
+        &[],
+        &[
+            "--output",
+            "csv",
+            "--sort",
+            "size",
+            "--max-depth",
+            "1",
+            "-type",
+            "f",
+        ],
+    )?;
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "path,type,size,mtime,mode");
+    for (line, prefix) in
+        lines[1..]
+            .iter()
+            .zip(["\"./a,\"\"b\"\"\",f,0,", "./small,f,1,", "./big,f,10,"])
+    {
+        assert!(line.starts_with(prefix), "{line}");
+    }
+
+    assert_eq!(
+        stdout_of(Some(dir.path()), &[], &["--output", "csv", "-false"])?,
+        "path,type,size,mtime,mode\n"
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn duplicates_ndjson_csv() -> Result<()> {
+    let dir = duplicates_fixture()?;
+
+    assert_eq!(
+        stdout_of(
+            Some(dir.path()),
+            &[],
+            &["--duplicates", "--output", "ndjson"]
+        )?,
+        "{\"paths\":[\"./big1\",\"./sub/big2\"],\"size\":10000}\n\
+         {\"paths\":[\"./a\",\"./sub/b\"],\"size\":3}\n"
+    );
+    assert_eq!(
+        stdout_of(Some(dir.path()), &[], &["--duplicates", "--output", "csv"])?,
+        "group,size,path\n\
+         1,10000,./big1\n\
+         1,10000,./sub/big2\n\
+         2,3,./a\n\
+         2,3,./sub/b\n"
+    );
+    Ok(())
+}
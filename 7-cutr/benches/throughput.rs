@@ -0,0 +1,81 @@
+//! Times cutr over a generated file of several million lines, in each mode.
+//!
+//! Run with `cargo bench -p cutr`, optionally with a filter on the mode
+//! names, as in `cargo bench -p cutr -- bytes`. `CUTR_BENCH_LINES` sets the
+//! number of lines.
+
+use std::env;
+use std::io::{BufWriter, Write};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 3;
+
+const MODES: &[(&str, &[&str])] = &[
+    ("fields csv", &["-f", "1,3,5"]),
+    ("fields plain", &["-f", "1,3,5", "--plain"]),
+    ("fields regex", &["-f", "1,3,5", "--regex-delimiter", r"\t"]),
+    ("fields complement", &["-f", "2", "--complement"]),
+    ("bytes", &["-b", "1-8,20-"]),
+    ("bytes whole chars", &["-b", "1-8,20-", "-n"]),
+    ("chars", &["-c", "1-8,20-"]),
+    ("graphemes", &["-c", "1-8,20-", "--graphemes"]),
+    ("width", &["-c", "1-8,20-", "--width"]),
+];
+
+fn main() {
+    let filters = env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
+    let lines = env::var("CUTR_BENCH_LINES")
+        .ok()
+        .and_then(|lines| lines.parse().ok())
+        .unwrap_or(2_000_000);
+
+    let input = tempfile::NamedTempFile::new().unwrap();
+    let mut writer = BufWriter::new(input.as_file());
+    for i in 0..lines {
+        writeln!(
+            writer,
+            "{i}\tuser{}\t{}.{:02}\tZoë Ångström 漢字\t2024-01-{:02}T12:00:00Z",
+            i % 1000,
+            i % 10_000,
+            i % 100,
+            i % 28 + 1
+        )
+        .unwrap();
+    }
+    drop(writer);
+    let size = input.as_file().metadata().unwrap().len();
+    println!("{lines} lines, {:.1} MB", size as f64 / 1e6);
+
+    for (name, args) in MODES {
+        if !filters.is_empty() && !filters.iter().any(|filter| name.contains(filter.as_str())) {
+            continue;
+        }
+
+        let best = (0..RUNS)
+            .map(|_| time(args, input.path().to_str().unwrap()))
+            .min()
+            .unwrap();
+        let secs = best.as_secs_f64();
+        println!(
+            "{name:<20} {secs:>7.3} s {:>8.1} MB/s {:>8.2} M lines/s",
+            size as f64 / 1e6 / secs,
+            lines as f64 / 1e6 / secs
+        );
+    }
+}
+
+fn time(args: &[&str], file: &str) -> Duration {
+    let start = Instant::now();
+    let status = Command::new(env!("CARGO_BIN_EXE_cutr"))
+        .args(args)
+        .arg(file)
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success(), "cutr {} failed", args.join(" "));
+    start.elapsed()
+}
@@ -1,34 +1,88 @@
-use std::ops::Range;
-
-pub fn extract_bytes(line: &str, byte_pos: &[Range<usize>]) -> String {
-    let bytes = line.as_bytes();
-
-    let result_u8: Vec<_> = byte_pos
-        .iter()
-        .cloned()
-        .flat_map(|range| {
-            bytes
-                .iter()
-                .skip(range.start)
-                .take(range.end - range.start)
-                .copied()
-        })
-        .collect();
+use crate::position_list::Position;
 
-    String::from_utf8_lossy(&result_u8).into_owned()
+/// Appends the selected bytes of `line` to `out`. With `whole_chars`, a
+/// range only takes a UTF-8 character if it includes the character's last
+/// byte, so that characters are never split.
+pub fn extract_bytes(line: &[u8], byte_pos: &[Position], whole_chars: bool, out: &mut Vec<u8>) {
+    for pos in byte_pos {
+        let range = pos.resolve(line.len());
+        if whole_chars {
+            out.extend_from_slice(
+                &line[char_start(line, range.start)..char_start(line, range.end)],
+            );
+        } else {
+            out.extend_from_slice(&line[range]);
+        }
+    }
+}
+
+/// The start of the character at byte `i`, or `i` itself at the end of
+/// `line`.
+fn char_start(line: &[u8], i: usize) -> usize {
+    // UTF-8 continuation bytes look like 0b10xx_xxxx.
+    (0..=i)
+        .rev()
+        .find(|&i| {
+            line.get(i)
+                .is_none_or(|&byte| byte & 0b1100_0000 != 0b1000_0000)
+        })
+        .unwrap_or(0)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn bytes(line: &[u8], byte_pos: &[Position], whole_chars: bool) -> Vec<u8> {
+        let mut out = vec![];
+        extract_bytes(line, byte_pos, whole_chars, &mut out);
+        out
+    }
+
+    fn lossy(line: &str, byte_pos: &[Position]) -> String {
+        String::from_utf8_lossy(&bytes(line.as_bytes(), byte_pos, false)).into_owned()
+    }
+
+    fn whole_chars(line: &str, byte_pos: &[Position]) -> String {
+        String::from_utf8(bytes(line.as_bytes(), byte_pos, true)).unwrap()
+    }
+
     #[test]
     fn test_extract_bytes() {
-        assert_eq!(extract_bytes("ábc", &[0..1]), "�".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..2]), "á".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..3]), "áb".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..4]), "ábc".to_string());
-        assert_eq!(extract_bytes("ábc", &[3..4, 2..3]), "cb".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..2, 5..6]), "á".to_string());
+        assert_eq!(lossy("ábc", &[(0..1).into()]), "�".to_string());
+        assert_eq!(lossy("ábc", &[(0..2).into()]), "á".to_string());
+        assert_eq!(lossy("ábc", &[(0..3).into()]), "áb".to_string());
+        assert_eq!(lossy("ábc", &[(0..4).into()]), "ábc".to_string());
+        assert_eq!(
+            lossy("ábc", &[(3..4).into(), (2..3).into()]),
+            "cb".to_string()
+        );
+        assert_eq!(
+            lossy("ábc", &[(0..2).into(), (5..6).into()]),
+            "á".to_string()
+        );
+        assert_eq!(lossy("ábc", &[(2..).into()]), "bc".to_string());
+        assert_eq!(lossy("ábc", &[(5..).into()]), "".to_string());
+    }
+
+    #[test]
+    fn test_extract_bytes_whole_chars() {
+        assert_eq!(whole_chars("ábc", &[(0..1).into()]), "");
+        assert_eq!(whole_chars("ábc", &[(1..2).into()]), "á");
+        assert_eq!(whole_chars("ábc", &[(0..2).into()]), "á");
+        assert_eq!(whole_chars("ábc", &[(1..3).into()]), "áb");
+        assert_eq!(whole_chars("ábc", &[(2..).into()]), "bc");
+        assert_eq!(whole_chars("ábc", &[(3..4).into(), (1..2).into()]), "cá");
+        assert_eq!(whole_chars("a漢b", &[(1..3).into()]), "");
+        assert_eq!(whole_chars("a漢b", &[(2..4).into()]), "漢");
+        assert_eq!(whole_chars("a漢b", &[(0..).into()]), "a漢b");
+    }
+
+    #[test]
+    fn test_extract_bytes_invalid_utf8() {
+        let line = b"a\xffb\xe6\xbc";
+        assert_eq!(bytes(line, &[(1..3).into()], false), b"\xffb");
+        assert_eq!(bytes(line, &[(3..).into()], false), b"\xe6\xbc");
+        assert_eq!(bytes(line, &[(0..3).into()], true), b"a\xffb");
     }
 }
@@ -1,25 +1,143 @@
-use std::ops::Range;
+use crate::position_list::Position;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
-pub fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String {
-    let chars = line.chars().collect::<Vec<_>>();
+/// Appends the selected chars of `line` to `out`.
+pub fn extract_chars(line: &str, char_pos: &[Position], out: &mut String) {
+    for pos in char_pos {
+        out.push_str(char_slice(line, pos));
+    }
+}
+
+/// The chars of `line` at `pos`, as a slice of it.
+pub fn char_slice<'a>(line: &'a str, pos: &Position) -> &'a str {
+    slice(line, line.char_indices().map(|(i, _)| i), pos)
+}
+
+/// Appends the selected grapheme clusters of `line` to `out`.
+pub fn extract_graphemes(line: &str, grapheme_pos: &[Position], out: &mut String) {
+    for pos in grapheme_pos {
+        out.push_str(slice(
+            line,
+            line.grapheme_indices(true).map(|(i, _)| i),
+            pos,
+        ));
+    }
+}
 
-    char_pos
-        .iter()
-        .flat_map(|range| chars.iter().skip(range.start).take(range.end - range.start))
-        .collect::<String>()
+/// The part of `line` at `pos`, given the byte offsets where each char or
+/// grapheme cluster starts.
+fn slice<'a>(line: &'a str, starts: impl Iterator<Item = usize>, pos: &Position) -> &'a str {
+    let mut offsets = starts.chain([line.len()]);
+    let start = offsets.nth(pos.start).unwrap_or(line.len());
+    let end = match pos.end {
+        None => line.len(),
+        Some(end) if end <= pos.start => start,
+        Some(end) => offsets.nth(end - pos.start - 1).unwrap_or(line.len()),
+    };
+    &line[start..end]
+}
+
+/// Appends the grapheme clusters of `line` in the selected display columns
+/// to `out`. A grapheme cluster is selected by the column it starts in, so
+/// that wide characters are never split.
+pub fn extract_columns(line: &str, column_pos: &[Position], out: &mut String) {
+    for pos in column_pos {
+        let mut column = 0;
+        for grapheme in line.graphemes(true) {
+            if pos.end.is_some_and(|end| column >= end) {
+                break;
+            }
+            if pos.contains(column) {
+                out.push_str(grapheme);
+            }
+            column += grapheme.width();
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn chars(line: &str, char_pos: &[Position]) -> String {
+        let mut out = String::new();
+        extract_chars(line, char_pos, &mut out);
+        out
+    }
+
+    fn graphemes(line: &str, grapheme_pos: &[Position]) -> String {
+        let mut out = String::new();
+        extract_graphemes(line, grapheme_pos, &mut out);
+        out
+    }
+
+    fn columns(line: &str, column_pos: &[Position]) -> String {
+        let mut out = String::new();
+        extract_columns(line, column_pos, &mut out);
+        out
+    }
+
     #[test]
     fn test_extract_chars() {
-        assert_eq!(extract_chars("", &[0..1]), "".to_string());
-        assert_eq!(extract_chars("ábc", &[0..1]), "á".to_string());
-        assert_eq!(extract_chars("ábc", &[0..1, 2..3]), "ác".to_string());
-        assert_eq!(extract_chars("ábc", &[0..3]), "ábc".to_string());
-        assert_eq!(extract_chars("ábc", &[2..3, 1..2]), "cb".to_string());
-        assert_eq!(extract_chars("ábc", &[0..1, 1..2, 4..5]), "áb".to_string());
+        assert_eq!(chars("", &[(0..1).into()]), "".to_string());
+        assert_eq!(chars("ábc", &[(0..1).into()]), "á".to_string());
+        assert_eq!(
+            chars("ábc", &[(0..1).into(), (2..3).into()]),
+            "ác".to_string()
+        );
+        assert_eq!(chars("ábc", &[(0..3).into()]), "ábc".to_string());
+        assert_eq!(
+            chars("ábc", &[(2..3).into(), (1..2).into()]),
+            "cb".to_string()
+        );
+        assert_eq!(
+            chars("ábc", &[(0..1).into(), (1..2).into(), (4..5).into()]),
+            "áb".to_string()
+        );
+        assert_eq!(chars("ábc", &[(1..).into()]), "bc".to_string());
+        assert_eq!(
+            chars("ábc", &[(2..).into(), (0..1).into()]),
+            "cá".to_string()
+        );
+    }
+
+    #[test]
+    fn test_char_slice() {
+        assert_eq!(char_slice("ábc", &(0..2).into()), "áb");
+        assert_eq!(char_slice("ábc", &(1..).into()), "bc");
+        assert_eq!(char_slice("ábc", &(2..9).into()), "c");
+        assert_eq!(char_slice("ábc", &(3..).into()), "");
+        assert_eq!(char_slice("ábc", &(5..9).into()), "");
+    }
+
+    #[test]
+    fn test_extract_graphemes() {
+        // "é" as "e" and a combining acute accent
+        let e_acute = "e\u{301}";
+        let line = format!("{e_acute}🇫🇷x");
+        assert_eq!(chars(&line, &[(0..1).into()]), "e");
+        assert_eq!(graphemes(&line, &[(0..1).into()]), e_acute);
+        assert_eq!(graphemes(&line, &[(1..2).into()]), "🇫🇷");
+        assert_eq!(graphemes(&line, &[(2..).into()]), "x");
+        assert_eq!(
+            graphemes(&line, &[(2..3).into(), (0..1).into()]),
+            format!("x{e_acute}")
+        );
+        assert_eq!(graphemes(&line, &[(3..4).into()]), "");
+        assert_eq!(graphemes("", &[(0..1).into()]), "");
+    }
+
+    #[test]
+    fn test_extract_columns() {
+        let line = "a漢e\u{301}字";
+        assert_eq!(columns(line, &[(0..1).into()]), "a");
+        assert_eq!(columns(line, &[(0..2).into()]), "a漢");
+        assert_eq!(columns(line, &[(1..3).into()]), "漢");
+        assert_eq!(columns(line, &[(2..3).into()]), "");
+        assert_eq!(columns(line, &[(3..4).into()]), "e\u{301}");
+        assert_eq!(columns(line, &[(4..).into()]), "字");
+        assert_eq!(columns(line, &[(4..6).into(), (0..1).into()]), "字a");
+        assert_eq!(columns(line, &[(6..).into()]), "");
     }
 }
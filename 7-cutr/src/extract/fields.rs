@@ -1,32 +1,71 @@
-use csv::StringRecord;
+use crate::position_list::Position;
+use regex::Regex;
 use std::ops::Range;
 
-pub fn extract_fields(record: &StringRecord, field_pos: &[Range<usize>]) -> Vec<String> {
-    let record = record.iter().collect::<Vec<_>>();
-
-    field_pos
-        .iter()
-        .flat_map(|range| {
-            record
-                .iter()
-                .skip(range.start)
-                .take(range.end - range.start)
-        })
-        .map(|field| field.to_string())
-        .collect()
+/// What separates fields when lines are split without CSV parsing.
+#[derive(Debug)]
+pub enum Delimiter {
+    Str(String),
+    Regex(Regex),
+}
+
+/// Fills `fields` with the byte ranges of the fields of `line`.
+pub fn split_fields(line: &str, delimiter: &Delimiter, fields: &mut Vec<Range<usize>>) {
+    fields.clear();
+    let mut start = 0;
+    let mut push = |delimiter: Range<usize>| {
+        fields.push(start..delimiter.start);
+        start = delimiter.end;
+    };
+    match delimiter {
+        Delimiter::Str(delimiter) => line
+            .match_indices(delimiter.as_str())
+            .for_each(|(i, delimiter)| push(i..i + delimiter.len())),
+        Delimiter::Regex(re) => re.find_iter(line).for_each(|m| push(m.range())),
+    }
+    fields.push(start..line.len());
+}
+
+/// The indices of the selected fields of a record with `len` fields.
+pub fn extract_fields(len: usize, field_pos: &[Position]) -> impl Iterator<Item = usize> + '_ {
+    field_pos.iter().flat_map(move |pos| pos.resolve(len))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn fields(len: usize, field_pos: &[Position]) -> Vec<usize> {
+        extract_fields(len, field_pos).collect()
+    }
+
+    fn split<'a>(line: &'a str, delimiter: &Delimiter) -> Vec<&'a str> {
+        let mut fields = vec![];
+        split_fields(line, delimiter, &mut fields);
+        fields.into_iter().map(|field| &line[field]).collect()
+    }
+
     #[test]
     fn test_extract_fields() {
-        let rec = StringRecord::from(vec!["Captain", "Sham", "12345"]);
-        assert_eq!(extract_fields(&rec, &[0..1]), &["Captain"]);
-        assert_eq!(extract_fields(&rec, &[1..2]), &["Sham"]);
-        assert_eq!(extract_fields(&rec, &[0..1, 2..3]), &["Captain", "12345"]);
-        assert_eq!(extract_fields(&rec, &[0..1, 3..4]), &["Captain"]);
-        assert_eq!(extract_fields(&rec, &[1..2, 0..1]), &["Sham", "Captain"]);
+        assert_eq!(fields(3, &[(0..1).into()]), &[0]);
+        assert_eq!(fields(3, &[(1..2).into()]), &[1]);
+        assert_eq!(fields(3, &[(0..1).into(), (2..3).into()]), &[0, 2]);
+        assert_eq!(fields(3, &[(0..1).into(), (3..4).into()]), &[0]);
+        assert_eq!(fields(3, &[(1..2).into(), (0..1).into()]), &[1, 0]);
+        assert_eq!(fields(3, &[(1..).into()]), &[1, 2]);
+        assert!(fields(0, &[(0..).into()]).is_empty());
+    }
+
+    #[test]
+    fn test_split_fields() {
+        let delimiter = Delimiter::Str("::".to_string());
+        assert_eq!(split("a::b::c", &delimiter), &["a", "b", "c"]);
+        assert_eq!(split("a:b::", &delimiter), &["a:b", ""]);
+        assert_eq!(split("", &delimiter), &[""]);
+
+        let delimiter = Delimiter::Regex(Regex::new(r"\s+").unwrap());
+        assert_eq!(split("a  b\t c", &delimiter), &["a", "b", "c"]);
+        assert_eq!(split("abc", &delimiter), &["abc"]);
+        assert_eq!(split(" a ", &delimiter), &["", "a", ""]);
     }
 }
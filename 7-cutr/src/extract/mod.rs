@@ -1,8 +1,7 @@
-#![allow(clippy::single_range_in_vec_init)]
 mod bytes;
 mod chars;
 mod fields;
 
 pub use bytes::extract_bytes;
-pub use chars::extract_chars;
-pub use fields::extract_fields;
+pub use chars::{char_slice, extract_chars, extract_columns, extract_graphemes};
+pub use fields::{extract_fields, split_fields, Delimiter};
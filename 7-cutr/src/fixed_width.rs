@@ -0,0 +1,128 @@
+use anyhow::{anyhow, bail, Result};
+use std::num::NonZeroUsize;
+
+use crate::extract::char_slice;
+use crate::position_list::Position;
+
+/// A named column of a fixed-width record.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Column {
+    pub name: String,
+    pub position: Position,
+}
+
+/// Parses a column spec with one `NAME START [WIDTH]` per line, where START
+/// counts chars from 1, and a column without WIDTH runs to the end of the
+/// line. Blank lines and lines starting with `#` are skipped.
+pub fn parse_spec(spec: &str) -> Result<Vec<Column>> {
+    let columns = spec
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| {
+            let error = |e: &dyn std::fmt::Display| anyhow!("line {}: {}", i + 1, e);
+            let number = |s: &str| {
+                s.parse::<NonZeroUsize>()
+                    .map(NonZeroUsize::get)
+                    .map_err(|_| error(&format!(r#"illegal number: "{}""#, s)))
+            };
+
+            let (name, start, width) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [name, start] => (name, number(start)?, None),
+                [name, start, width] => (name, number(start)?, Some(number(width)?)),
+                _ => return Err(error(&"expected NAME START [WIDTH]")),
+            };
+
+            let start = start - 1;
+            Ok(Column {
+                name: name.to_string(),
+                position: match width {
+                    Some(width) => Position::from(start..start + width),
+                    None => Position::from(start..),
+                },
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if columns.is_empty() {
+        bail!("no columns");
+    }
+    Ok(columns)
+}
+
+/// The value of each column in `line`, with padding trimmed if `trim`.
+pub fn extract_record<'a>(
+    line: &'a str,
+    columns: &'a [Column],
+    trim: bool,
+) -> impl Iterator<Item = &'a str> {
+    columns.iter().map(move |column| {
+        let value = char_slice(line, &column.position);
+        if trim {
+            value.trim()
+        } else {
+            value
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spec() {
+        let spec = "\
+# name start width
+id     1  4
+name   5  10
+
+note   15
+";
+        assert_eq!(
+            parse_spec(spec).unwrap(),
+            vec![
+                Column {
+                    name: "id".to_string(),
+                    position: (0..4).into(),
+                },
+                Column {
+                    name: "name".to_string(),
+                    position: (4..14).into(),
+                },
+                Column {
+                    name: "note".to_string(),
+                    position: (14..).into(),
+                },
+            ]
+        );
+
+        for (spec, error) in [
+            ("id", "line 1: expected NAME START [WIDTH]"),
+            ("id 1 2 3", "line 1: expected NAME START [WIDTH]"),
+            ("id 1 2\nname 0 3", r#"line 2: illegal number: "0""#),
+            ("id 1 x", r#"line 1: illegal number: "x""#),
+            ("id -1 2", r#"line 1: illegal number: "-1""#),
+            ("# nothing\n", "no columns"),
+        ] {
+            assert_eq!(parse_spec(spec).unwrap_err().to_string(), error);
+        }
+    }
+
+    #[test]
+    fn test_extract_record() {
+        let extract_record =
+            |line, columns, trim| extract_record(line, columns, trim).collect::<Vec<_>>();
+        let columns = parse_spec("id 1 4\nname 5 8\nnote 13").unwrap();
+        let line = "0042Zoë     à la carte";
+        assert_eq!(
+            extract_record(line, &columns, false),
+            &["0042", "Zoë     ", "à la carte"]
+        );
+        assert_eq!(
+            extract_record(line, &columns, true),
+            &["0042", "Zoë", "à la carte"]
+        );
+        assert_eq!(extract_record("12", &columns, false), &["12", "", ""]);
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::{bail, Result};
+use serde_json::Value;
+use std::borrow::Cow;
+
+/// A path into a JSON value like `.user.id` or `.items.0.name`, where a
+/// number also indexes arrays, and `.` alone is the whole value.
+#[derive(Debug, PartialEq, Eq)]
+pub struct JsonPath {
+    text: String,
+    keys: Vec<String>,
+}
+
+impl std::fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl JsonPath {
+    /// The value at the path, or `None` if a key or index is missing.
+    pub fn lookup<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        self.keys.iter().try_fold(value, |value, key| match value {
+            Value::Object(object) => object.get(key),
+            Value::Array(array) => array.get(key.parse::<usize>().ok()?),
+            _ => None,
+        })
+    }
+}
+
+/// Parses comma-separated paths.
+pub fn parse_paths(list: &str) -> Result<Vec<JsonPath>> {
+    list.split(',').map(parse_path).collect()
+}
+
+fn parse_path(text: &str) -> Result<JsonPath> {
+    let keys = match text.strip_prefix('.') {
+        Some("") => vec![],
+        Some(rest) if !rest.split('.').any(str::is_empty) => {
+            rest.split('.').map(str::to_string).collect()
+        }
+        _ => bail!(r#"illegal JSON path: "{}""#, text),
+    };
+
+    Ok(JsonPath {
+        text: text.to_string(),
+        keys,
+    })
+}
+
+/// Strings as they are, `null` as empty, and anything else as compact JSON.
+pub fn render(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::String(s) => Cow::Borrowed(s),
+        Value::Null => Cow::Borrowed(""),
+        value => Cow::Owned(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_paths() {
+        let paths = parse_paths(".user.id,.status,.").unwrap();
+        assert_eq!(paths[0].keys, &["user", "id"]);
+        assert_eq!(paths[1].keys, &["status"]);
+        assert!(paths[2].keys.is_empty());
+        assert_eq!(paths[0].to_string(), ".user.id");
+
+        for bad in ["", "user", ".user..id", ".user.", ".a,"] {
+            assert!(parse_paths(bad).is_err(), "{bad}");
+        }
+        assert_eq!(
+            parse_paths("id").unwrap_err().to_string(),
+            r#"illegal JSON path: "id""#
+        );
+    }
+
+    #[test]
+    fn test_lookup() {
+        let value = json!({"user": {"id": 7, "tags": ["a", "b"]}, "status": null});
+        let lookup = |path| parse_paths(path).unwrap()[0].lookup(&value).cloned();
+
+        assert_eq!(lookup(".user.id"), Some(json!(7)));
+        assert_eq!(lookup(".user.tags.1"), Some(json!("b")));
+        assert_eq!(lookup(".status"), Some(json!(null)));
+        assert_eq!(lookup("."), Some(value.clone()));
+        assert_eq!(lookup(".user.tags.2"), None);
+        assert_eq!(lookup(".user.tags.x"), None);
+        assert_eq!(lookup(".user.id.x"), None);
+        assert_eq!(lookup(".missing"), None);
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(render(&json!("a\tb")), "a\tb");
+        assert_eq!(render(&json!(null)), "");
+        assert_eq!(render(&json!(1.5)), "1.5");
+        assert_eq!(render(&json!(true)), "true");
+        assert_eq!(render(&json!({"a": [1, 2]})), r#"{"a":[1,2]}"#);
+    }
+}
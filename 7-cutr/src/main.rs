@@ -1,14 +1,23 @@
-use crate::extract::{extract_bytes, extract_chars, extract_fields};
-use anyhow::{bail, Result};
-use clap::Parser;
-use csv::{ReaderBuilder, WriterBuilder};
-use std::fs::File;
+use crate::extract::{
+    extract_bytes, extract_chars, extract_columns, extract_fields, extract_graphemes, split_fields,
+    Delimiter,
+};
+use anyhow::{anyhow, bail, Result};
+use clap::{Parser, ValueEnum};
+use csv::{ReaderBuilder, StringRecord};
+use regex::Regex;
+use std::fs::{self, File};
 use std::io;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::mem;
 
-use crate::position_list::PositionList;
+use crate::fixed_width::{extract_record, parse_spec, Column};
+use crate::jsonl::{parse_paths, render, JsonPath};
+use crate::position_list::{resolve_names, Position, PositionList};
 
 mod extract;
+mod fixed_width;
+mod jsonl;
 mod position_list;
 
 #[derive(Debug, Parser)]
@@ -16,31 +25,149 @@ mod position_list;
 struct Args {
     #[arg(default_value = "-")]
     files: Vec<String>,
-    #[arg(short, long, default_value = "\t")]
+    /// Field delimiter, of one or more characters
+    #[arg(short, long, default_value = "\t", conflicts_with = "regex_delimiter")]
     delimiter: String,
+    /// Split fields on matches of REGEX instead, such as '\s+'
+    #[arg(long, value_name = "REGEX")]
+    regex_delimiter: Option<Regex>,
+    /// Delimiter between output fields [default: the --delimiter]
+    #[arg(long, value_name = "DELIM")]
+    output_delimiter: Option<String>,
     #[command(flatten)]
     extract: ArgsExtract,
+    /// Select everything but the given fields, bytes or chars
+    #[arg(long)]
+    complement: bool,
+    /// Output in the order selected, repeats included, instead of input order
+    #[arg(long, conflicts_with = "complement")]
+    reorder: bool,
+    /// Parse fields as CSV, with quoting [default: with a single-byte delimiter]
+    #[arg(long, conflicts_with_all = ["regex_delimiter", "bytes", "chars"])]
+    csv: bool,
+    /// Split lines on the delimiter alone, with no quote processing
+    #[arg(long, conflicts_with_all = ["csv", "bytes", "chars"])]
+    plain: bool,
+    /// With --chars, count extended grapheme clusters, like "é" or "🇫🇷"
+    #[arg(long, conflicts_with_all = ["width", "fields", "fields_by_name", "bytes"])]
+    graphemes: bool,
+    /// With --chars, count terminal display columns
+    #[arg(long, conflicts_with_all = ["fields", "fields_by_name", "bytes"])]
+    width: bool,
+    /// With --bytes, don't split multibyte characters
+    #[arg(short = 'n', conflicts_with_all = ["fields", "fields_by_name", "chars"])]
+    no_split: bool,
+    /// With --bytes, write the selected bytes unchanged instead of as UTF-8
+    #[arg(long, conflicts_with_all = ["fields", "fields_by_name", "chars"])]
+    raw: bool,
+    /// Don't print lines without a delimiter
+    #[arg(short = 's', long, conflicts_with_all = ["bytes", "chars"])]
+    only_delimited: bool,
+    /// With --fixed-width, the output format
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Format::Tsv,
+        conflicts_with_all = ["fields", "fields_by_name", "bytes", "chars"]
+    )]
+    format: Format,
+    /// With --fixed-width, trim the whitespace padding columns
+    #[arg(long, conflicts_with_all = ["fields", "fields_by_name", "bytes", "chars"])]
+    trim: bool,
+    /// Read JSON Lines, selecting values with --fields by JSON path, such as
+    /// '.user.id,.status'
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "fields_by_name", "bytes", "chars", "fixed_width", "regex_delimiter",
+            "complement", "reorder", "only_delimited",
+        ]
+    )]
+    jsonl: bool,
+    /// With --jsonl, fail on a missing key instead of leaving it empty
+    #[arg(long, requires = "jsonl")]
+    strict: bool,
 }
 
 #[derive(Debug, clap::Args)]
 #[group(required = true, multiple = false)]
 struct ArgsExtract {
-    /// Selected fields
-    #[arg(short, long, value_name = "FIELDS")]
+    /// Selected fields, or JSON paths with --jsonl
+    #[arg(short, long, value_name = "FIELDS", allow_hyphen_values = true)]
     fields: Option<String>,
+    /// Selected fields, by their names in the header row
+    #[arg(short = 'F', long, value_name = "NAMES")]
+    fields_by_name: Option<String>,
     /// Selected bytes
-    #[arg(short, long, value_name = "BYTES")]
+    #[arg(short, long, value_name = "BYTES", allow_hyphen_values = true)]
     bytes: Option<String>,
     /// Selected chars
-    #[arg(short, long, value_name = "CHARS")]
+    #[arg(short, long, value_name = "CHARS", allow_hyphen_values = true)]
     chars: Option<String>,
+    /// Named columns of fixed-width records, one `NAME START [WIDTH]` per
+    /// line of SPEC, counting chars from 1
+    #[arg(
+        long,
+        value_name = "SPEC",
+        conflicts_with_all = [
+            "complement", "reorder", "csv", "plain", "graphemes", "width",
+            "no_split", "raw", "only_delimited",
+        ]
+    )]
+    fixed_width: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Tsv,
+    Csv,
+    Json,
+}
+
+/// How fields are split and joined.
+#[derive(Debug)]
+struct FieldOptions {
+    delimiter: Delimiter,
+    output_delimiter: String,
+    csv: bool,
+    only_delimited: bool,
+}
+
+/// How the positions given on the command line become the ones extracted.
+#[derive(Debug, Clone, Copy)]
+struct Selection {
+    complement: bool,
+    reorder: bool,
+}
+
+impl Selection {
+    fn apply(self, positions: PositionList) -> PositionList {
+        if self.complement {
+            position_list::complement(&positions)
+        } else if self.reorder {
+            positions
+        } else {
+            position_list::merge(&positions)
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum Extract {
-    Fields(PositionList),
+    Fields(Fields),
     Bytes(PositionList),
     Chars(PositionList),
+    Graphemes(PositionList),
+    Columns(PositionList),
+    FixedWidth(Vec<Column>),
+    Json(Vec<JsonPath>),
+}
+
+#[derive(Debug)]
+pub enum Fields {
+    Positions(PositionList),
+    /// Header names, resolved against the first row of each file.
+    Names(String),
 }
 
 fn main() {
@@ -52,74 +179,424 @@ fn main() {
 }
 
 fn run(args: Args) -> Result<()> {
-    let delimiter = parse_delimiter(&args.delimiter)?;
+    let delimiter = match args.regex_delimiter {
+        Some(re) => parse_regex_delimiter(re)?,
+        None => parse_delimiter(&args.delimiter)?,
+    };
+    let single_byte = matches!(&delimiter, Delimiter::Str(delimiter) if delimiter.len() == 1);
+    if args.csv && !single_byte {
+        bail!(
+            "--csv needs a single-byte --delim, not \"{}\"",
+            args.delimiter
+        );
+    }
+    let field_options = FieldOptions {
+        delimiter,
+        output_delimiter: args.output_delimiter.unwrap_or(args.delimiter),
+        csv: single_byte && !args.plain,
+        only_delimited: args.only_delimited,
+    };
 
+    let selection = Selection {
+        complement: args.complement,
+        reorder: args.reorder,
+    };
+    let parse_pos = |list| Ok::<_, anyhow::Error>(selection.apply(position_list::parse_pos(list)?));
     let extract = match args.extract {
+        ArgsExtract {
+            fields: Some(paths),
+            ..
+        } if args.jsonl => Extract::Json(parse_paths(&paths)?),
         ArgsExtract {
             fields: Some(fields),
             ..
-        } => Extract::Fields(position_list::parse_pos(fields)?),
+        } => Extract::Fields(Fields::Positions(parse_pos(fields)?)),
+        ArgsExtract {
+            fields_by_name: Some(names),
+            ..
+        } => Extract::Fields(Fields::Names(names)),
         ArgsExtract {
             bytes: Some(bytes), ..
-        } => Extract::Bytes(position_list::parse_pos(bytes)?),
+        } => Extract::Bytes(parse_pos(bytes)?),
         ArgsExtract {
             chars: Some(chars), ..
-        } => Extract::Chars(position_list::parse_pos(chars)?),
-        _ => unreachable!("Must have --fields, --bytes, or --chars"),
+        } if args.graphemes => Extract::Graphemes(parse_pos(chars)?),
+        ArgsExtract {
+            chars: Some(chars), ..
+        } if args.width => Extract::Columns(parse_pos(chars)?),
+        ArgsExtract {
+            chars: Some(chars), ..
+        } => Extract::Chars(parse_pos(chars)?),
+        ArgsExtract {
+            fixed_width: Some(spec),
+            ..
+        } => Extract::FixedWidth(
+            fs::read_to_string(&spec)
+                .map_err(anyhow::Error::from)
+                .and_then(|text| parse_spec(&text))
+                .map_err(|e| anyhow!("{spec}: {e}"))?,
+        ),
+        _ => unreachable!("Must have --fields, --bytes, --chars, or --fixed-width"),
     };
 
+    let mut out = BufWriter::new(io::stdout().lock());
+    // The header row of --fixed-width, written once for all files.
+    let mut header = true;
     for filename in &args.files {
         match open(filename) {
             Ok(file) => match &extract {
                 Extract::Fields(fields) => {
-                    let mut reader = ReaderBuilder::new()
-                        .has_headers(false)
-                        .delimiter(delimiter)
-                        .from_reader(file);
-
-                    let mut writer = WriterBuilder::new()
-                        .delimiter(delimiter)
-                        .from_writer(io::stdout());
-
-                    for result in reader.records() {
-                        let record = result?;
-                        let fields = extract_fields(&record, fields);
-                        writer.write_record(&fields)?;
-                    }
+                    cut_fields(file, &mut out, fields, &field_options, selection)?
                 }
-                Extract::Bytes(bytes) => {
-                    for line in file.lines() {
-                        let line = line?;
-                        let extracted = extract_bytes(&line, bytes);
-                        println!("{}", extracted);
-                    }
-                }
-                Extract::Chars(chars) => {
-                    for line in file.lines() {
-                        let line = line?;
-                        let extracted = extract_chars(&line, chars);
-                        println!("{}", extracted);
-                    }
+                Extract::Bytes(bytes) => cut_bytes(file, &mut out, bytes, args.no_split, args.raw)?,
+                Extract::Chars(chars) => cut_lines(file, &mut out, |line, extracted| {
+                    extract_chars(line, chars, extracted)
+                })?,
+                Extract::Graphemes(graphemes) => cut_lines(file, &mut out, |line, extracted| {
+                    extract_graphemes(line, graphemes, extracted)
+                })?,
+                Extract::Columns(columns) => cut_lines(file, &mut out, |line, extracted| {
+                    extract_columns(line, columns, extracted)
+                })?,
+                Extract::FixedWidth(columns) => cut_fixed_width(
+                    file,
+                    &mut out,
+                    columns,
+                    args.format,
+                    args.trim,
+                    mem::take(&mut header),
+                )?,
+                Extract::Json(paths) => {
+                    cut_jsonl(file, &mut out, paths, &field_options, args.strict)?
                 }
             },
             Err(e) => {
+                out.flush()?;
                 eprintln!("{filename}: {e}");
                 continue;
             }
         }
     }
 
+    out.flush()?;
+    Ok(())
+}
+
+/// Reads the next line into `line`, without its line ending, like `lines()`
+/// does but into the same buffer each time. False at the end of the input.
+fn next_line(file: &mut impl BufRead, line: &mut String) -> io::Result<bool> {
+    line.clear();
+    if file.read_line(line)? == 0 {
+        return Ok(false);
+    }
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(true)
+}
+
+/// Reads lines as bytes, so that input which isn't UTF-8 can be cut too.
+fn cut_bytes(
+    mut file: Box<dyn BufRead>,
+    out: &mut impl Write,
+    positions: &[Position],
+    whole_chars: bool,
+    raw: bool,
+) -> Result<()> {
+    let mut line = vec![];
+    let mut extracted = vec![];
+    while file.read_until(b'\n', &mut line)? > 0 {
+        if line.last() == Some(&b'\n') {
+            line.pop();
+            // Like lines(), except in raw mode, which changes no bytes.
+            if !raw && line.last() == Some(&b'\r') {
+                line.pop();
+            }
+        }
+
+        extracted.clear();
+        extract_bytes(&line, positions, whole_chars, &mut extracted);
+        if raw {
+            out.write_all(&extracted)?;
+        } else {
+            out.write_all(String::from_utf8_lossy(&extracted).as_bytes())?;
+        }
+        out.write_all(b"\n")?;
+        line.clear();
+    }
+
+    Ok(())
+}
+
+fn cut_lines(
+    mut file: Box<dyn BufRead>,
+    out: &mut impl Write,
+    extract: impl Fn(&str, &mut String),
+) -> Result<()> {
+    let mut line = String::new();
+    let mut extracted = String::new();
+    while next_line(&mut file, &mut line)? {
+        extracted.clear();
+        extract(&line, &mut extracted);
+        out.write_all(extracted.as_bytes())?;
+        out.write_all(b"\n")?;
+    }
+
     Ok(())
 }
 
-fn parse_delimiter(delim: &str) -> Result<u8> {
-    let bytes = delim.as_bytes();
+fn cut_fixed_width(
+    mut file: Box<dyn BufRead>,
+    out: &mut impl Write,
+    columns: &[Column],
+    format: Format,
+    trim: bool,
+    header: bool,
+) -> Result<()> {
+    let names = || columns.iter().map(|column| column.name.as_str());
+    let mut line = String::new();
 
-    if bytes.len() == 1 {
-        Ok(bytes[0])
-    } else {
-        bail!("--delim \"{}\" must be a single byte", delim);
+    let mut writer = match format {
+        Format::Tsv => FieldWriter::new(out, b"\t", false),
+        Format::Csv => FieldWriter::new(out, b",", true),
+        Format::Json => {
+            while next_line(&mut file, &mut line)? {
+                let record = names().zip(extract_record(&line, columns, trim));
+                for (i, (name, value)) in record.enumerate() {
+                    out.write_all(if i == 0 { b"{" } else { b"," })?;
+                    serde_json::to_writer(&mut *out, name)?;
+                    out.write_all(b":")?;
+                    serde_json::to_writer(&mut *out, value)?;
+                }
+                out.write_all(b"}\n")?;
+            }
+            return Ok(());
+        }
+    };
+
+    if header {
+        writer.write(names())?;
+    }
+    while next_line(&mut file, &mut line)? {
+        writer.write(extract_record(&line, columns, trim))?;
     }
+    Ok(())
+}
+
+fn cut_jsonl(
+    mut file: Box<dyn BufRead>,
+    out: &mut impl Write,
+    paths: &[JsonPath],
+    options: &FieldOptions,
+    strict: bool,
+) -> Result<()> {
+    // A missing key is empty, even as the only value.
+    let mut writer = FieldWriter::new(out, options.output_delimiter.as_bytes(), options.csv)
+        .without_lone_empty_quotes();
+    let mut line = String::new();
+    let mut line_number = 0;
+    while next_line(&mut file, &mut line)? {
+        line_number += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value = serde_json::from_str(&line).map_err(|e| anyhow!("line {line_number}: {e}"))?;
+
+        let values = paths
+            .iter()
+            .map(|path| match path.lookup(&value) {
+                Some(value) => Ok(render(value)),
+                None if strict => bail!("line {line_number}: missing {path}"),
+                None => Ok("".into()),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        writer.write(values.iter().map(|value| value.as_bytes()))?;
+    }
+
+    Ok(())
+}
+
+/// Writes records of fields joined by a delimiter, quoting them as CSV does
+/// if `csv`.
+struct FieldWriter<'a, W: Write> {
+    out: &'a mut W,
+    delimiter: &'a [u8],
+    csv: bool,
+    /// Quote a record of one empty field as `""`.
+    quote_lone_empty: bool,
+}
+
+impl<'a, W: Write> FieldWriter<'a, W> {
+    /// Only quotes if the delimiter is a single byte, as CSV needs.
+    fn new(out: &'a mut W, delimiter: &'a [u8], csv: bool) -> Self {
+        Self {
+            out,
+            delimiter,
+            csv: csv && delimiter.len() == 1,
+            quote_lone_empty: true,
+        }
+    }
+
+    /// Writes a record of one empty field as an empty line instead.
+    fn without_lone_empty_quotes(self) -> Self {
+        Self {
+            quote_lone_empty: false,
+            ..self
+        }
+    }
+
+    fn write<T: AsRef<[u8]>>(&mut self, fields: impl IntoIterator<Item = T>) -> Result<()> {
+        let mut fields = fields.into_iter().peekable();
+        let mut first = true;
+        while let Some(field) = fields.next() {
+            let field = field.as_ref();
+            if !first {
+                self.out.write_all(self.delimiter)?;
+            }
+            // Like the csv crate, which also quotes a record of one empty
+            // field, so that it isn't read back as a blank line.
+            let lone_empty =
+                self.quote_lone_empty && first && field.is_empty() && fields.peek().is_none();
+            if self.csv && (lone_empty || self.needs_quotes(field)) {
+                self.out.write_all(b"\"")?;
+                for (i, part) in field.split(|&b| b == b'"').enumerate() {
+                    if i > 0 {
+                        self.out.write_all(b"\"\"")?;
+                    }
+                    self.out.write_all(part)?;
+                }
+                self.out.write_all(b"\"")?;
+            } else {
+                self.out.write_all(field)?;
+            }
+            first = false;
+        }
+        self.out.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn needs_quotes(&self, field: &[u8]) -> bool {
+        field
+            .iter()
+            .any(|&b| b == self.delimiter[0] || matches!(b, b'"' | b'\r' | b'\n'))
+    }
+}
+
+/// Writes the selected fields of a record of `len` fields, where `field(i)`
+/// is the field at `i`.
+fn write_record<'r, W: Write>(
+    writer: &mut FieldWriter<W>,
+    options: &FieldOptions,
+    positions: &[Position],
+    len: usize,
+    field: impl Fn(usize) -> &'r str,
+) -> Result<()> {
+    // A line without the delimiter is a single field.
+    if options.only_delimited && len < 2 {
+        return Ok(());
+    }
+    writer.write(extract_fields(len, positions).map(field))
+}
+
+fn cut_fields<W: Write>(
+    mut file: Box<dyn BufRead>,
+    out: &mut W,
+    fields: &Fields,
+    options: &FieldOptions,
+    selection: Selection,
+) -> Result<()> {
+    let mut writer = FieldWriter::new(out, options.output_delimiter.as_bytes(), options.csv);
+    // Resolves the names against the header row, which is kept in the output.
+    let resolve = |writer: &mut FieldWriter<W>, names: &str, headers: &[&str]| {
+        let positions = selection.apply(resolve_names(names, headers)?);
+        write_record(writer, options, &positions, headers.len(), |i| headers[i])?;
+        Ok::<_, anyhow::Error>(positions)
+    };
+
+    let resolved;
+    match &options.delimiter {
+        Delimiter::Str(delimiter) if options.csv => {
+            let mut reader = ReaderBuilder::new()
+                .flexible(true)
+                .has_headers(matches!(fields, Fields::Names(_)))
+                .delimiter(delimiter.as_bytes()[0])
+                .from_reader(file);
+
+            let positions = match fields {
+                Fields::Positions(positions) => positions,
+                Fields::Names(names) => {
+                    let headers = reader.headers()?;
+                    if headers.is_empty() {
+                        return Ok(());
+                    }
+                    resolved = resolve(&mut writer, names, &headers.iter().collect::<Vec<_>>())?;
+                    &resolved
+                }
+            };
+
+            let mut record = StringRecord::new();
+            while reader.read_record(&mut record)? {
+                write_record(&mut writer, options, positions, record.len(), |i| {
+                    &record[i]
+                })?;
+            }
+        }
+        delimiter => {
+            let mut line = String::new();
+            let mut ranges = vec![];
+            let positions = match fields {
+                Fields::Positions(positions) => positions,
+                Fields::Names(names) => {
+                    if !next_line(&mut file, &mut line)? {
+                        return Ok(());
+                    }
+                    split_fields(&line, delimiter, &mut ranges);
+                    let headers = ranges
+                        .iter()
+                        .map(|range| &line[range.clone()])
+                        .collect::<Vec<_>>();
+                    resolved = resolve(&mut writer, names, &headers)?;
+                    &resolved
+                }
+            };
+
+            while next_line(&mut file, &mut line)? {
+                split_fields(&line, delimiter, &mut ranges);
+                // Like cut, pass a line without the delimiter through intact.
+                if ranges.len() < 2 && !options.only_delimited {
+                    writer.write([line.as_str()])?;
+                    continue;
+                }
+                write_record(&mut writer, options, positions, ranges.len(), |i| {
+                    &line[ranges[i].clone()]
+                })?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_delimiter(delim: &str) -> Result<Delimiter> {
+    if delim.is_empty() {
+        bail!("--delim \"{}\" must not be empty", delim);
+    }
+
+    Ok(Delimiter::Str(delim.to_string()))
+}
+
+fn parse_regex_delimiter(re: Regex) -> Result<Delimiter> {
+    if re.is_match("") {
+        bail!(
+            "--regex-delimiter \"{}\" must not match an empty string",
+            re
+        );
+    }
+
+    Ok(Delimiter::Regex(re))
 }
 
 fn open(filename: &str) -> Result<Box<dyn BufRead>> {
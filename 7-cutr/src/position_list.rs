@@ -1,18 +1,56 @@
 use std::num::NonZeroUsize;
-use std::ops::Range;
+use std::ops::{Range, RangeFrom};
 use std::sync::OnceLock;
 
 use anyhow::{anyhow, bail, Context, Result};
 use regex::Regex;
 
-pub type PositionList = Vec<Range<usize>>;
+/// Zero-based positions from `start` up to, but excluding, `end`, or to the
+/// end of the line if there is no `end`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    pub start: usize,
+    pub end: Option<usize>,
+}
+
+impl Position {
+    /// The part of this position that falls within `len` items.
+    pub fn resolve(&self, len: usize) -> Range<usize> {
+        let end = self.end.map_or(len, |end| end.min(len));
+        self.start.min(end)..end
+    }
+
+    pub fn contains(&self, i: usize) -> bool {
+        i >= self.start && self.end.is_none_or(|end| i < end)
+    }
+}
+
+impl From<Range<usize>> for Position {
+    fn from(range: Range<usize>) -> Self {
+        Self {
+            start: range.start,
+            end: Some(range.end),
+        }
+    }
+}
+
+impl From<RangeFrom<usize>> for Position {
+    fn from(range: RangeFrom<usize>) -> Self {
+        Self {
+            start: range.start,
+            end: None,
+        }
+    }
+}
+
+pub type PositionList = Vec<Position>;
 
 pub fn parse_pos(ranges: String) -> Result<PositionList> {
     ranges
         .split(',')
         .map(|range| {
             parse_index(range)
-                .map(|i| i..i + 1)
+                .map(|i| Position::from(i..i + 1))
                 .or_else(|_| parse_range(range))
         })
         .collect::<Result<Vec<_>>>()
@@ -30,20 +68,28 @@ pub fn parse_index(range: &str) -> Result<usize> {
         .map_err(|_| value_error())
 }
 
-const RANGE_RE_STR: &str = r#"^(\d+)-(\d+)$"#;
+const RANGE_RE_STR: &str = r#"^(\d*)-(\d*)$"#;
 static RANGE_RE: OnceLock<Regex> = OnceLock::new();
 
-pub fn parse_range(range: &str) -> Result<Range<usize>> {
+/// Parses `N-M`, or the open-ended `-M` and `N-`.
+pub fn parse_range(range: &str) -> Result<Position> {
     let re = RANGE_RE.get_or_init(|| Regex::new(RANGE_RE_STR).unwrap());
 
     let captures = re
         .captures(range)
+        .filter(|captures| !captures[1].is_empty() || !captures[2].is_empty())
         .with_context(|| format!("illegal list value: \"{}\"", range))?;
 
-    let start = parse_index(&captures[1])?;
-    let end = parse_index(&captures[2])?;
+    let start = match &captures[1] {
+        "" => 0,
+        start => parse_index(start)?,
+    };
+    let end = match &captures[2] {
+        "" => return Ok(Position::from(start..)),
+        end => parse_index(end)?,
+    };
 
-    if start >= end {
+    if !captures[1].is_empty() && start >= end {
         bail!(
             "First number in range ({}) must be lower than second number ({})",
             start + 1,
@@ -51,7 +97,85 @@ pub fn parse_range(range: &str) -> Result<Range<usize>> {
         );
     }
 
-    Ok(start..end + 1)
+    Ok(Position::from(start..end + 1))
+}
+
+/// Resolves a comma-separated list of header names, or ranges of them like
+/// `title-year`, to the positions of those fields in `headers`.
+pub fn resolve_names(names: &str, headers: &[&str]) -> Result<PositionList> {
+    let index = |name: &str| headers.iter().position(|&header| header == name);
+
+    names
+        .split(',')
+        .map(|name| {
+            if let Some(i) = index(name) {
+                return Ok(Position::from(i..i + 1));
+            }
+
+            // Names may contain '-' themselves, so try each one as the
+            // separator of a range.
+            for (i, _) in name.match_indices('-') {
+                let (first, second) = (&name[..i], &name[i + 1..]);
+                let start = match first {
+                    "" => Some(0),
+                    first => index(first),
+                };
+
+                let position = match (start, second) {
+                    (None, _) => continue,
+                    (Some(_), "") if first.is_empty() => continue,
+                    (Some(start), "") => Position::from(start..),
+                    (Some(start), second) => match index(second) {
+                        None => continue,
+                        Some(end) if start > end => bail!(
+                            "First field in range ({}) must come before second field ({})",
+                            first,
+                            second
+                        ),
+                        Some(end) => Position::from(start..end + 1),
+                    },
+                };
+                return Ok(position);
+            }
+
+            bail!(r#"unknown field name: "{}""#, name)
+        })
+        .collect()
+}
+
+/// Sorts `positions` and merges the ones that overlap or touch, so that each
+/// position is selected once, in input order.
+pub fn merge(positions: &[Position]) -> PositionList {
+    let mut sorted = positions.to_vec();
+    sorted.sort_by_key(|position| position.start);
+
+    let mut merged: PositionList = vec![];
+    for position in sorted {
+        match merged.last_mut() {
+            Some(last) if last.end.is_none_or(|end| position.start <= end) => {
+                last.end = last.end.zip(position.end).map(|(a, b)| a.max(b));
+            }
+            _ => merged.push(position),
+        }
+    }
+    merged
+}
+
+/// Every position not in `positions`, in ascending order.
+pub fn complement(positions: &[Position]) -> PositionList {
+    let mut complement = vec![];
+    let mut start = 0;
+    for position in merge(positions) {
+        if position.start > start {
+            complement.push(Position::from(start..position.start));
+        }
+        match position.end {
+            Some(end) => start = end,
+            None => return complement,
+        }
+    }
+    complement.push(Position::from(start..));
+    complement
 }
 
 #[cfg(test)]
@@ -118,15 +242,19 @@ mod tests {
         let res = parse_pos("1,".to_string());
         assert!(res.is_err());
 
-        let res = parse_pos("1-".to_string());
-        assert!(res.is_err());
-
         let res = parse_pos("1-1-1".to_string());
         assert!(res.is_err());
 
         let res = parse_pos("1-1-a".to_string());
         assert!(res.is_err());
 
+        let res = parse_pos("--1".to_string());
+        assert!(res.is_err());
+
+        let res = parse_pos("-0".to_string());
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), r#"illegal list value: "0""#);
+
         // First number must be less than second
         let res = parse_pos("1-1".to_string());
         assert!(res.is_err());
@@ -145,34 +273,127 @@ mod tests {
         // All the following are acceptable
         let res = parse_pos("1".to_string());
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..1]);
+        assert_eq!(res.unwrap(), vec![(0..1).into()]);
 
         let res = parse_pos("01".to_string());
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..1]);
+        assert_eq!(res.unwrap(), vec![(0..1).into()]);
 
         let res = parse_pos("1,3".to_string());
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..1, 2..3]);
+        assert_eq!(res.unwrap(), vec![(0..1).into(), (2..3).into()]);
 
         let res = parse_pos("001,0003".to_string());
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..1, 2..3]);
+        assert_eq!(res.unwrap(), vec![(0..1).into(), (2..3).into()]);
 
         let res = parse_pos("1-3".to_string());
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..3]);
+        assert_eq!(res.unwrap(), vec![(0..3).into()]);
 
         let res = parse_pos("0001-03".to_string());
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..3]);
+        assert_eq!(res.unwrap(), vec![(0..3).into()]);
 
         let res = parse_pos("1,7,3-5".to_string());
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..1, 6..7, 2..5]);
+        assert_eq!(
+            res.unwrap(),
+            vec![(0..1).into(), (6..7).into(), (2..5).into()]
+        );
 
         let res = parse_pos("15,19-20".to_string());
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![14..15, 18..20]);
+        assert_eq!(res.unwrap(), vec![(14..15).into(), (18..20).into()]);
+
+        // Open-ended ranges
+        let res = parse_pos("-3".to_string());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![(0..3).into()]);
+
+        let res = parse_pos("5-".to_string());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![(4..).into()]);
+
+        let res = parse_pos("1-".to_string());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![(0..).into()]);
+
+        let res = parse_pos("-1,3-".to_string());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![(0..1).into(), (2..).into()]);
+    }
+
+    #[test]
+    fn test_resolve_names() {
+        let headers = ["title", "year", "first-name", "director"];
+        let resolve = |names| resolve_names(names, &headers);
+
+        assert_eq!(resolve("title").unwrap(), vec![(0..1).into()]);
+        assert_eq!(
+            resolve("director,title").unwrap(),
+            vec![(3..4).into(), (0..1).into()]
+        );
+        assert_eq!(resolve("first-name").unwrap(), vec![(2..3).into()]);
+        assert_eq!(resolve("title-year").unwrap(), vec![(0..2).into()]);
+        assert_eq!(resolve("year-first-name").unwrap(), vec![(1..3).into()]);
+        assert_eq!(resolve("first-name-director").unwrap(), vec![(2..4).into()]);
+        assert_eq!(resolve("year-year").unwrap(), vec![(1..2).into()]);
+        assert_eq!(resolve("-year").unwrap(), vec![(0..2).into()]);
+        assert_eq!(resolve("year-").unwrap(), vec![(1..).into()]);
+
+        for (names, error) in [
+            ("author", r#"unknown field name: "author""#),
+            ("title,", r#"unknown field name: """#),
+            ("-", r#"unknown field name: "-""#),
+            ("title-author", r#"unknown field name: "title-author""#),
+            (
+                "year-title",
+                "First field in range (year) must come before second field (title)",
+            ),
+        ] {
+            assert_eq!(resolve(names).unwrap_err().to_string(), error);
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(Position::from(1..3).resolve(5), 1..3);
+        assert_eq!(Position::from(1..3).resolve(2), 1..2);
+        assert_eq!(Position::from(4..6).resolve(2), 2..2);
+        assert_eq!(Position::from(1..).resolve(5), 1..5);
+        assert_eq!(Position::from(7..).resolve(5), 5..5);
+    }
+
+    #[test]
+    fn test_merge() {
+        let positions: PositionList = vec![(2..3).into(), (0..1).into()];
+        assert_eq!(merge(&positions), vec![(0..1).into(), (2..3).into()]);
+
+        let positions: PositionList = vec![(0..3).into(), (1..2).into(), (0..1).into()];
+        assert_eq!(merge(&positions), vec![(0..3).into()]);
+
+        let positions: PositionList = vec![(3..5).into(), (0..2).into(), (2..3).into()];
+        assert_eq!(merge(&positions), vec![(0..5).into()]);
+
+        let positions: PositionList = vec![(4..).into(), (1..2).into(), (5..7).into()];
+        assert_eq!(merge(&positions), vec![(1..2).into(), (4..).into()]);
+
+        assert_eq!(merge(&[]), vec![]);
+    }
+
+    #[test]
+    fn test_complement() {
+        let positions: PositionList = vec![(2..4).into(), (0..1).into(), (3..5).into()];
+        assert_eq!(complement(&positions), vec![(1..2).into(), (5..).into()]);
+
+        let positions: PositionList = vec![(1..2).into(), (4..).into()];
+        assert_eq!(complement(&positions), vec![(0..1).into(), (2..4).into()]);
+
+        let positions: PositionList = vec![(0..2).into(), (2..3).into()];
+        assert_eq!(complement(&positions), vec![(3..).into()]);
+
+        let positions: PositionList = vec![(0..).into(), (1..2).into()];
+        assert_eq!(complement(&positions), vec![]);
     }
 }
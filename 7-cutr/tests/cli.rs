@@ -9,6 +9,8 @@ const PRG: &str = "cutr";
 const CSV: &str = "tests/inputs/movies1.csv";
 const TSV: &str = "tests/inputs/movies1.tsv";
 const BOOKS: &str = "tests/inputs/books.tsv";
+const SPEC: &str = "tests/inputs/people.spec";
+const PEOPLE: &str = "tests/inputs/people.txt";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -58,7 +60,8 @@ fn dies_not_enough_args() -> Result<()> {
     dies(
         &[CSV],
         "the following required arguments were not provided:\n  \
-        <--fields <FIELDS>|--bytes <BYTES>|--chars <CHARS>>",
+        <--fields <FIELDS>|--fields-by-name <NAMES>|--bytes <BYTES>|--chars <CHARS>|\
+        --fixed-width <SPEC>>",
     )
 }
 
@@ -97,16 +100,34 @@ fn dies_bad_digit_chars() -> Result<()> {
 fn dies_empty_delimiter() -> Result<()> {
     dies(
         &[CSV, "-f", "1", "-d", ""],
-        r#"--delim "" must be a single byte"#,
+        r#"--delim "" must not be empty"#,
     )
 }
 
 // --------------------------------------------------
 #[test]
-fn dies_bad_delimiter() -> Result<()> {
+fn dies_empty_regex_delimiter() -> Result<()> {
     dies(
-        &[CSV, "-f", "1", "-d", ",,"],
-        r#"--delim ",," must be a single byte"#,
+        &[CSV, "-f", "1", "--regex-delimiter", ",*"],
+        r#"--regex-delimiter ",*" must not match an empty string"#,
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_regex_delimiter() -> Result<()> {
+    dies(
+        &[CSV, "-f", "1", "--regex-delimiter", "("],
+        "invalid value '(' for '--regex-delimiter <REGEX>'",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_delimiter_and_regex_delimiter() -> Result<()> {
+    dies(
+        &[CSV, "-f", "1", "-d", ",", "--regex-delimiter", ","],
+        "cannot be used with",
     )
 }
 
@@ -338,5 +359,418 @@ fn tsv_c1_8() -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn repeated_value() -> Result<()> {
-    run(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1,1.out")
+    run(
+        &[BOOKS, "-c", "1,1", "--reorder"],
+        "tests/expected/books.c1,1.out",
+    )
+}
+
+// --------------------------------------------------
+fn run_stdin(args: &[&str], input: &str, expected: &str) -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected.to_string());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn multi_byte_delimiter() -> Result<()> {
+    run_stdin(
+        &["-d", "::", "-f", "1,3"],
+        "a::b::c\nd:e::f\n\"g::h\"\n",
+        "a::c\nd:e\n\"g\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn regex_delimiter() -> Result<()> {
+    run_stdin(
+        &["--regex-delimiter", r"\s+", "-f", "2-3"],
+        "a  b\t c\none\n",
+        "b\tc\none\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn output_delimiter() -> Result<()> {
+    run_stdin(
+        &["-d", ",", "--output-delimiter", " | ", "-f", "1,3"],
+        "Jules Verne,1870,\"20,000 Leagues\"\n",
+        "Jules Verne | 20,000 Leagues\n",
+    )?;
+    run_stdin(
+        &[
+            "-d",
+            ",",
+            "--output-delimiter",
+            ";",
+            "-f",
+            "3,1",
+            "--reorder",
+        ],
+        "Jules Verne,1870,\"20,000; Leagues\"\n",
+        "\"20,000; Leagues\";Jules Verne\n",
+    )?;
+    run_stdin(
+        &[
+            "--regex-delimiter",
+            " +",
+            "--output-delimiter",
+            "::",
+            "-f",
+            "1-2",
+        ],
+        "a  b c\n",
+        "a::b\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn open_ended_ranges() -> Result<()> {
+    let input = "a\tb\tc\td\n";
+    run_stdin(&["-f", "-2"], input, "a\tb\n")?;
+    run_stdin(&["-f", "3-"], input, "c\td\n")?;
+    run_stdin(&["-f", "1-"], input, input)?;
+    run_stdin(&["-c", "-3"], "ábcd\n", "ábc\n")?;
+    run_stdin(&["-b", "4-"], "ábcd\n", "cd\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn complement() -> Result<()> {
+    let input = "a\tb\tc\td\n";
+    run_stdin(&["-f", "2", "--complement"], input, "a\tc\td\n")?;
+    run_stdin(&["-f", "3-,1", "--complement"], input, "b\n")?;
+    run_stdin(&["-f", "1-", "--complement"], input, "\n")?;
+    run_stdin(&["-c", "2-3", "--complement"], "ábcd\n", "ád\n")?;
+    run_stdin(&["-b", "-2", "--complement"], "ábcd\n", "bcd\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn fields_by_name() -> Result<()> {
+    let books = "tests/inputs/books.csv";
+    run_stdin(
+        &["-d", ",", "-F", "Title,Author", "--reorder", books],
+        "",
+        "Title,Author\n\
+         La Confession de Claude,Émile Zola\n\
+         Waiting for Godot,Samuel Beckett\n\
+         \"20,000 Leagues Under the Sea\",Jules Verne\n",
+    )?;
+    run_stdin(
+        &["--fields-by-name", "Year-Title", "tests/inputs/books.tsv"],
+        "",
+        "Year\tTitle\n\
+         1865\tLa Confession de Claude\n\
+         1952\tWaiting for Godot\n\
+         1870\t20,000 Leagues Under the Sea\n",
+    )?;
+    run_stdin(
+        &["-d", ",", "-F", "Year", "--complement", books],
+        "",
+        "Author,Title\n\
+         Émile Zola,La Confession de Claude\n\
+         Samuel Beckett,Waiting for Godot\n\
+         Jules Verne,\"20,000 Leagues Under the Sea\"\n",
+    )?;
+    run_stdin(&["-d", "::", "-F", "b"], "a::b\n1::2\n", "b\n2\n")?;
+    run_stdin(&["-F", "b"], "", "")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_unknown_field_name() -> Result<()> {
+    dies(
+        &[
+            "-d",
+            ",",
+            "-F",
+            "Author,Publisher",
+            "tests/inputs/books.csv",
+        ],
+        r#"unknown field name: "Publisher""#,
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn input_order() -> Result<()> {
+    let input = "a\tb\tc\td\n";
+    run_stdin(&["-f", "3,1"], input, "a\tc\n")?;
+    run_stdin(&["-f", "1-3,2"], input, "a\tb\tc\n")?;
+    run_stdin(&["-f", "4,2-"], input, "b\tc\td\n")?;
+    run_stdin(&["-c", "3,1,1"], "ábc\n", "ác\n")?;
+    run_stdin(&["-b", "4,1-3,2"], "ábcd\n", "ábc\n")?;
+    run_stdin(&[BOOKS, "-c", "1,1"], "", "A\nÉ\nS\nJ\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn reorder() -> Result<()> {
+    let input = "a\tb\tc\td\n";
+    run_stdin(&["-f", "3,1", "--reorder"], input, "c\ta\n")?;
+    run_stdin(&["-f", "1-3,2", "--reorder"], input, "a\tb\tc\tb\n")?;
+    run_stdin(&["-c", "3,1,1", "--reorder"], "ábc\n", "cáá\n")?;
+    run_stdin(
+        &["-d", ",", "-F", "Title,Author", "--reorder"],
+        "Author,Title\nZola,Nana\n",
+        "Title,Author\nNana,Zola\n",
+    )?;
+    run_stdin(
+        &["-d", ",", "-F", "Title,Author"],
+        "Author,Title\nZola,Nana\n",
+        "Author,Title\nZola,Nana\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn plain_fields() -> Result<()> {
+    let input = "Jules Verne,1870,\"20,000 Leagues\"\n";
+    run_stdin(&["-d", ",", "-f", "3", "--plain"], input, "\"20\n")?;
+    run_stdin(
+        &["-d", ",", "-f", "3-", "--plain"],
+        input,
+        "\"20,000 Leagues\"\n",
+    )?;
+    run_stdin(
+        &["-d", ",", "-f", "3", "--csv"],
+        input,
+        "\"20,000 Leagues\"\n",
+    )?;
+    run_stdin(&["-d", ",", "-f", "1", "--plain"], "\"a\",b\n", "\"a\"\n")?;
+    run_stdin(&["-d", ",", "-f", "1", "--csv"], "\"a\",b\n", "a\n")?;
+    run_stdin(
+        &["-d", ",", "-f", "1,3", "--plain", "--output-delimiter", ";"],
+        "a,\"b;c\",d\n",
+        "a;d\n",
+    )?;
+    run_stdin(
+        &[
+            "--regex-delimiter",
+            " +",
+            "-f",
+            "1-2",
+            "--output-delimiter",
+            ",",
+        ],
+        "a,b  c\n",
+        "a,b,c\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn only_delimited() -> Result<()> {
+    let input = "a\tb\nno delimiter\n\nc\td\n";
+    run_stdin(&["-f", "2", "--plain"], input, "b\nno delimiter\n\nd\n")?;
+    run_stdin(&["-d", "::", "-f", "2"], "a::b\nc:d\n", "b\nc:d\n")?;
+    run_stdin(&["-f", "2", "-s"], input, "b\nd\n")?;
+    run_stdin(&["-f", "1", "--only-delimited", "--plain"], input, "a\nc\n")?;
+    run_stdin(&["-d", "::", "-f", "1", "-s"], "a::b\nc:d\n", "a\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_csv_multi_byte_delimiter() -> Result<()> {
+    dies(
+        &[CSV, "-f", "1", "-d", "::", "--csv"],
+        r#"--csv needs a single-byte --delim, not "::""#,
+    )?;
+    dies(&[CSV, "-f", "1", "--csv", "--plain"], "cannot be used with")?;
+    dies(&[CSV, "-c", "1", "-s"], "cannot be used with")
+}
+
+// --------------------------------------------------
+#[test]
+fn graphemes() -> Result<()> {
+    let input = "e\u{301}🇫🇷x\n";
+    run_stdin(&["-c", "1"], input, "e\n")?;
+    run_stdin(&["-c", "1", "--graphemes"], input, "e\u{301}\n")?;
+    run_stdin(&["-c", "2-", "--graphemes"], input, "🇫🇷x\n")?;
+    run_stdin(
+        &["-c", "2", "--graphemes", "--complement"],
+        input,
+        "e\u{301}x\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn width() -> Result<()> {
+    let input = "漢字ab\n";
+    run_stdin(&["-c", "1-2", "--width"], input, "漢\n")?;
+    run_stdin(&["-c", "2-4", "--width"], input, "字\n")?;
+    run_stdin(&["-c", "5-", "--width"], input, "ab\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_graphemes_width() -> Result<()> {
+    dies(&[CSV, "-f", "1", "--graphemes"], "cannot be used with")?;
+    dies(&[CSV, "-b", "1", "--width"], "cannot be used with")?;
+    dies(
+        &[CSV, "-c", "1", "--width", "--graphemes"],
+        "cannot be used with",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn no_split() -> Result<()> {
+    let input = "ábc\na漢b\n";
+    run_stdin(&["-b", "1"], input, "�\na\n")?;
+    run_stdin(&["-b", "1", "-n"], input, "\na\n")?;
+    run_stdin(&["-b", "2-3", "-n"], input, "áb\n\n")?;
+    run_stdin(&["-b", "-3", "-n"], input, "áb\na\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn raw_bytes() -> Result<()> {
+    let input: &[u8] = b"a\xffb\xe6\xbc\r\n\x00\x01\x02\n";
+    Command::cargo_bin(PRG)?
+        .args(["-b", "2-", "--raw"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(&b"\xffb\xe6\xbc\r\n\x01\x02\n"[..]);
+    Command::cargo_bin(PRG)?
+        .args(["-b", "2-3"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("\u{fffd}b\n\u{1}\u{2}\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_no_split_raw_without_bytes() -> Result<()> {
+    dies(&[CSV, "-c", "1", "-n"], "cannot be used with")?;
+    dies(&[CSV, "-f", "1", "--raw"], "cannot be used with")
+}
+
+// --------------------------------------------------
+#[test]
+fn fixed_width() -> Result<()> {
+    let stdout = |args: &[&str]| -> Result<String> {
+        let output = Command::cargo_bin(PRG)?.args(args).output()?;
+        assert!(output.status.success());
+        Ok(String::from_utf8(output.stdout)?)
+    };
+
+    assert_eq!(
+        stdout(&["--fixed-width", SPEC, PEOPLE])?,
+        "id\tname\tcity\n\
+        0001\tZoë       \tMontréal\n\
+        0002\tBob, Jr.  \t\"Paris\"\n\
+        0003\tAnn\t\n"
+    );
+    assert_eq!(
+        stdout(&["--fixed-width", SPEC, "--trim", "--format", "csv", PEOPLE])?,
+        "id,name,city\n\
+        0001,Zoë,Montréal\n\
+        0002,\"Bob, Jr.\",\"\"\"Paris\"\"\"\n\
+        0003,Ann,\n"
+    );
+    assert_eq!(
+        stdout(&["--fixed-width", SPEC, "--trim", "--format", "json", PEOPLE])?,
+        "{\"id\":\"0001\",\"name\":\"Zoë\",\"city\":\"Montréal\"}\n\
+        {\"id\":\"0002\",\"name\":\"Bob, Jr.\",\"city\":\"\\\"Paris\\\"\"}\n\
+        {\"id\":\"0003\",\"name\":\"Ann\",\"city\":\"\"}\n"
+    );
+    // The header is written once for all files.
+    assert_eq!(
+        stdout(&["--fixed-width", SPEC, "--trim", PEOPLE, PEOPLE])?
+            .lines()
+            .count(),
+        7
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_fixed_width_spec() -> Result<()> {
+    dies(
+        &["--fixed-width", PEOPLE],
+        r#"tests/inputs/people.txt: line 1: illegal number: "Montréal""#,
+    )?;
+    let bad = gen_bad_file();
+    dies(&["--fixed-width", &bad], &format!("{bad}: "))?;
+    dies(
+        &["--fixed-width", SPEC, "--complement"],
+        "cannot be used with",
+    )?;
+    dies(&[CSV, "-f", "1", "--trim"], "cannot be used with")?;
+    dies(&[CSV, "-c", "1", "--format", "json"], "cannot be used with")
+}
+
+// --------------------------------------------------
+#[test]
+fn jsonl() -> Result<()> {
+    let input = r#"{"user": {"id": 7, "name": "Ann, B"}, "status": "ok"}
+
+{"user": {"id": 8, "tags": ["x"]}, "status": null}
+{"status": "a\tb"}
+"#;
+    run_stdin(
+        &["--jsonl", "-f", ".user.id,.status"],
+        input,
+        "7\tok\n8\t\n\t\"a\tb\"\n",
+    )?;
+    run_stdin(
+        &["--jsonl", "-f", ".user.name,.user.tags", "-d", ","],
+        input,
+        "\"Ann, B\",\n,\"[\"\"x\"\"]\"\n,\n",
+    )?;
+    run_stdin(
+        &[
+            "--jsonl",
+            "-f",
+            ".user.tags.0,.user.id",
+            "--plain",
+            "-d",
+            ",",
+        ],
+        input,
+        ",7\nx,8\n,\n",
+    )?;
+    // A missing key is empty, not "", even as the only value.
+    run_stdin(&["--jsonl", "-f", ".user.id"], input, "7\n8\n\n")?;
+    run_stdin(&["--jsonl", "-f", ".user.id", "-d", ","], input, "7\n8\n\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_jsonl() -> Result<()> {
+    let input = "{\"user\": {\"id\": 7}}\n{\"status\": \"ok\"}\n";
+    Command::cargo_bin(PRG)?
+        .args(["--jsonl", "--strict", "-f", ".user.id"])
+        .write_stdin(input)
+        .assert()
+        .failure()
+        .stdout("7\n")
+        .stderr("line 2: missing .user.id\n");
+    Command::cargo_bin(PRG)?
+        .args(["--jsonl", "-f", ".a"])
+        .write_stdin("{\"a\": 1}\n{\"a\": 1\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with("line 2: "));
+    dies(
+        &["--jsonl", "-f", "user.id"],
+        r#"illegal JSON path: "user.id""#,
+    )?;
+    dies(&["--jsonl", "-c", "1"], "cannot be used with")?;
+    dies(&[CSV, "--strict", "-f", "1"], "--jsonl")
 }
@@ -0,0 +1,4 @@
+# name  start  width
+id      1      4
+name    5      10
+city    15